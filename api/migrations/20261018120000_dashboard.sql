@@ -0,0 +1,11 @@
+ALTER TABLE events ADD COLUMN project TEXT;
+
+CREATE INDEX events_user_id_created_at_idx ON events (user_id, created_at);
+
+CREATE TABLE sessions (
+    id INT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    token TEXT UNIQUE NOT NULL,
+    user_id INT NOT NULL REFERENCES users(id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,8 @@
+ALTER TABLE users ADD COLUMN public_badges BOOL NOT NULL DEFAULT FALSE;
+
+CREATE TABLE public_projects (
+    user_id INT NOT NULL REFERENCES users(id),
+    project TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, project)
+);
@@ -0,0 +1,6 @@
+ALTER TABLE users
+    ADD COLUMN public_profile BOOL NOT NULL DEFAULT FALSE,
+    ADD COLUMN profile_languages BOOL NOT NULL DEFAULT TRUE,
+    ADD COLUMN profile_weekly_totals BOOL NOT NULL DEFAULT TRUE,
+    ADD COLUMN profile_streaks BOOL NOT NULL DEFAULT TRUE,
+    ADD COLUMN profile_projects BOOL NOT NULL DEFAULT FALSE;
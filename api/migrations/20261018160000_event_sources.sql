@@ -0,0 +1,8 @@
+ALTER TABLE events
+    ADD COLUMN editor TEXT,
+    ADD COLUMN editor_version TEXT,
+    ADD COLUMN client_version TEXT,
+    ADD COLUMN os TEXT,
+    ADD COLUMN arch TEXT,
+    ADD COLUMN machine_id TEXT,
+    ADD COLUMN hostname TEXT;
@@ -0,0 +1,3 @@
+ALTER TABLE events
+    ADD COLUMN lines_added INT NOT NULL DEFAULT 0,
+    ADD COLUMN lines_removed INT NOT NULL DEFAULT 0;
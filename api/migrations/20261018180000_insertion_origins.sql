@@ -0,0 +1,4 @@
+ALTER TABLE events
+    ADD COLUMN typed_chars INT NOT NULL DEFAULT 0,
+    ADD COLUMN pasted_chars INT NOT NULL DEFAULT 0,
+    ADD COLUMN bulk_chars INT NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+ALTER TABLE events
+    ADD COLUMN kind TEXT NOT NULL DEFAULT 'editor',
+    ADD COLUMN command TEXT,
+    ADD COLUMN duration_seconds INT;
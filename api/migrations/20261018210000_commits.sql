@@ -0,0 +1,17 @@
+CREATE TABLE commits (
+    id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id),
+    project TEXT NOT NULL,
+    sha TEXT NOT NULL,
+    -- `checkout` rows record a switch of branch, ending the time of the previous commit
+    kind TEXT NOT NULL,
+    branch TEXT,
+    author_name TEXT,
+    author_email TEXT,
+    files TEXT[] NOT NULL,
+    authored_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE UNIQUE INDEX commits_sha_idx ON commits (user_id, project, sha) WHERE kind = 'commit';
+CREATE INDEX commits_user_id_idx ON commits (user_id, created_at);
@@ -0,0 +1,4 @@
+ALTER TABLE events ADD COLUMN branch TEXT;
+
+-- Subject line, searched for ticket keys
+ALTER TABLE commits ADD COLUMN message TEXT;
@@ -0,0 +1,10 @@
+-- Days a user has sent events on, claimed by the first event of each day so
+-- concurrent requests don't both announce it
+CREATE TABLE active_days (
+    user_id INT NOT NULL REFERENCES users(id),
+    day DATE NOT NULL,
+    PRIMARY KEY (user_id, day)
+);
+
+INSERT INTO active_days (user_id, day)
+SELECT DISTINCT user_id, (created_at AT TIME ZONE 'UTC')::DATE FROM events;
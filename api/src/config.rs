@@ -1,3 +1,11 @@
+use crate::logging::LogFormat;
+use axum::http::HeaderValue;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    str::FromStr,
+};
+
 #[derive(clap::Parser, Clone)]
 pub struct Config {
     #[clap(long, env)]
@@ -8,4 +16,121 @@ pub struct Config {
 
     #[clap(short, env, default_value = "3000")]
     pub port: u16,
+
+    /// Address to listen on, either `host:port` (`[::]:3000` for IPv6) or
+    /// `unix:/path/to.sock`. Defaults to every IPv4 interface on `--port`
+    #[clap(long, env)]
+    pub bind: Option<BindAddress>,
+
+    /// Address serving `/metrics` to Prometheus, like `127.0.0.1:9090`, away
+    /// from the public listener. Metrics aren't served when it is unset
+    #[clap(long, env)]
+    pub metrics_bind: Option<SocketAddr>,
+
+    /// PEM certificate chain, serving HTTPS together with `--tls-key`
+    #[clap(long, env, requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of `--tls-cert`
+    #[clap(long, env, requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Marks session cookies `Secure`, so browsers only send them over
+    /// HTTPS. Only turn it off when serving plain HTTP on localhost
+    #[clap(long, env, default_value_t = true, action = clap::ArgAction::Set)]
+    pub secure_cookies: bool,
+
+    /// Seconds between checks for a renewed certificate or key
+    #[clap(long, env, default_value = "60", value_parser = clap::value_parser!(u64).range(1..))]
+    pub tls_reload_interval: u64,
+
+    /// Comma separated origins allowed to call the API from a browser, `*` allows any
+    #[clap(long, env, value_delimiter = ',', value_parser = parse_origin)]
+    pub cors_origins: Vec<HeaderValue>,
+
+    /// Requests per minute accepted on `/events/capture` for each user
+    #[clap(long, env, default_value = "120")]
+    pub capture_rate_limit: u32,
+
+    /// Requests per minute accepted on `/auth/login` for each client IP
+    #[clap(long, env, default_value = "10")]
+    pub login_rate_limit: u32,
+
+    /// Requests per minute accepted on badges and public profiles for each
+    /// client IP
+    #[clap(long, env, default_value = "300")]
+    pub public_rate_limit: u32,
+
+    /// Comma separated addresses of the reverse proxies in front of the API,
+    /// whose `X-Forwarded-For` tells the client IP the rate limits go by.
+    /// Over a Unix socket the proxy is `::1`
+    #[clap(long, env, value_delimiter = ',')]
+    pub trusted_proxies: Vec<IpAddr>,
+
+    /// Comma separated GitHub usernames allowed to register global webhooks
+    #[clap(long, env, value_delimiter = ',')]
+    pub admins: Vec<String>,
+
+    #[clap(long, env, value_enum, default_value = "json")]
+    pub log_format: LogFormat,
+
+    /// Client ID of the GitHub OAuth app used to sign in to the dashboard
+    #[clap(long, env, requires = "github_client_secret")]
+    pub github_client_id: Option<String>,
+
+    #[clap(long, env, requires = "github_client_id")]
+    pub github_client_secret: Option<String>,
+
+    /// Regex of the ticket keys found in branch names and commit messages, for
+    /// the time per ticket report. Repeat the flag for more, or join them
+    /// with `|` in the environment variable
+    #[clap(
+        long,
+        env,
+        default_values = [r"\b[A-Z][A-Z0-9]{1,9}-[1-9][0-9]*\b", r"#[1-9][0-9]*\b"]
+    )]
+    pub ticket_patterns: Vec<regex::Regex>,
+}
+
+impl Config {
+    pub fn bind_address(&self) -> BindAddress {
+        self.bind
+            .clone()
+            .unwrap_or(BindAddress::Tcp(SocketAddr::from((
+                Ipv4Addr::UNSPECIFIED,
+                self.port,
+            ))))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for BindAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+
+        s.parse()
+            .map(Self::Tcp)
+            .map_err(|e| format!("invalid bind address `{s}`: {e}"))
+    }
+}
+
+/// `*`, or an origin the way browsers send it, like `https://example.com`.
+fn parse_origin(s: &str) -> Result<HeaderValue, String> {
+    let is_origin = reqwest::Url::parse(s).is_ok_and(|url| url.origin().ascii_serialization() == s);
+    if s != "*" && !is_origin {
+        return Err(format!(
+            "invalid origin `{s}`, expected `*` or `scheme://host[:port]` without a trailing slash"
+        ));
+    }
+
+    HeaderValue::from_str(s).map_err(|e| format!("invalid origin `{s}`: {e}"))
 }
@@ -1,15 +1,36 @@
-use crate::http::{AppState, Error, Result};
-use axum::extract::{Json, State};
+use crate::{
+    http::{AppState, Error, Result, error::ErrorMessage, extractor::JsonBody},
+    queries::user::UserId,
+    webhooks::Event,
+};
+use anyhow::Context;
+use axum::{
+    extract::{Json, Query, State},
+    http::{StatusCode, header::ACCEPT},
+    response::Redirect,
+};
+use axum_extra::{
+    TypedHeader,
+    extract::cookie::{Cookie, CookieJar, SameSite},
+    headers::{Authorization, authorization::Bearer},
+};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use time::OffsetDateTime;
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
+const OAUTH_STATE_COOKIE: &str = "cairos_oauth_state";
+
+#[derive(Deserialize, ToSchema)]
 pub struct LoginRequest {
+    /// GitHub OAuth access token
     pub access_token: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct LoginResponse {
+    /// Cairos API token to send as `Authorization: Bearer <token>`
     pub token: String,
 }
 
@@ -25,25 +46,88 @@ struct GitHubEmail {
     primary: bool,
 }
 
+/// Exchanges a GitHub access token for a Cairos API token
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Logged in", body = LoginResponse),
+        (status = 429, description = "Rate limit exceeded", body = ErrorMessage,
+            headers(("Retry-After" = u64, description = "Seconds until a request is accepted again"))),
+        (status = 500, description = "GitHub rejected the token or could not be reached", body = ErrorMessage),
+    )
+)]
 pub async fn login(
     State(state): State<AppState>,
-    Json(payload): Json<LoginRequest>,
+    JsonBody(payload): JsonBody<LoginRequest>,
 ) -> Result<Json<LoginResponse>> {
+    let result = login_with_github(&state, &payload.access_token).await;
+
+    let outcome = if result.is_ok() { "success" } else { "failure" };
+    metrics::counter!("login_attempts_total", "outcome" => outcome).increment(1);
+
+    result.map(Json)
+}
+
+async fn login_with_github(state: &AppState, access_token: &str) -> Result<LoginResponse> {
+    let user_id = upsert_github_user(state, access_token).await?;
+    let token = generate_token();
+
+    crate::queries::auth_tokens::create(&state.db, user_id, &token, OffsetDateTime::now_utc())
+        .await?;
+    crate::webhooks::enqueue(&state.db, user_id, Event::TokenCreated, json!({})).await;
+
+    Ok(LoginResponse { token })
+}
+
+/// Revokes the token used to authenticate the request
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+    )
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<StatusCode> {
+    let Some(user_id) = crate::queries::auth_tokens::disable(
+        &state.db,
+        authorization.token(),
+        OffsetDateTime::now_utc(),
+    )
+    .await?
+    else {
+        return Err(Error::Unauthorized {
+            message: "Invalid token".to_owned(),
+        });
+    };
+
+    crate::webhooks::enqueue(&state.db, user_id, Event::TokenRevoked, json!({})).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Creates or updates the user owning a GitHub access token.
+pub async fn upsert_github_user(state: &AppState, access_token: &str) -> Result<UserId> {
     let user_response = state
         .client
         .get("https://api.github.com/user")
-        .bearer_auth(&payload.access_token)
+        .bearer_auth(access_token)
         .send()
         .await
-        .map_err(|e| {
-            log::error!("Error on get user: {e}");
-            Error::InternalServerError
-        })?;
+        .context("Error on get user")?;
 
-    let github_user: GitHubUser = user_response.json().await.map_err(|e| {
-        log::error!("Error on jsonfy: {e}");
-        Error::InternalServerError
-    })?;
+    let github_user: GitHubUser = user_response
+        .json()
+        .await
+        .context("Error on deserialize user_response")?;
 
     let email = if let Some(email) = github_user.email {
         email
@@ -51,47 +135,126 @@ pub async fn login(
         let email_response = state
             .client
             .get("https://api.github.com/user/emails")
-            .bearer_auth(&payload.access_token)
+            .bearer_auth(access_token)
             .send()
             .await
-            .map_err(|e| {
-                log::error!("Error on request github email: {e}");
-                Error::InternalServerError
-            })?;
+            .context("Error on request github email")?;
 
-        let emails: Vec<GitHubEmail> = email_response.json().await.map_err(|e| {
-            log::error!("Error on request deserialize email_response: {e}");
-            Error::InternalServerError
-        })?;
+        let emails: Vec<GitHubEmail> = email_response
+            .json()
+            .await
+            .context("Error on deserialize email_response")?;
 
         let Some(email) = emails
             .iter()
             .find(|email| email.primary)
             .map(|s| s.email.clone())
         else {
-            return Err(Error::InternalServerError);
+            return Err(anyhow::anyhow!("GitHub user has no primary email").into());
         };
 
         email
     };
 
-    let now = time::OffsetDateTime::now_utc();
-
     let user_id = crate::queries::user::create(
         &state.db,
         &crate::queries::user::CreateParams {
             username: github_user.login,
             email,
-            now,
+            now: OffsetDateTime::now_utc(),
         },
     )
     .await?;
 
-    let token = generate_token();
+    Ok(user_id)
+}
 
-    crate::queries::auth_tokens::create(&state.db, user_id, &token, now).await?;
+/// Starts the GitHub OAuth web flow used to sign in to the dashboard
+pub async fn github_authorize(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Redirect)> {
+    let Some(client_id) = &state.config.github_client_id else {
+        return Err(github_oauth_disabled());
+    };
+
+    let csrf_state = generate_token();
+    let url = reqwest::Url::parse_with_params(
+        "https://github.com/login/oauth/authorize",
+        &[
+            ("client_id", client_id.as_str()),
+            ("scope", "read:user user:email"),
+            ("state", csrf_state.as_str()),
+        ],
+    )
+    .context("Error on build GitHub authorize URL")?;
+
+    let cookie = Cookie::build((OAUTH_STATE_COOKIE, csrf_state))
+        .path("/auth/github")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::minutes(10));
+
+    Ok((jar.add(cookie), Redirect::to(url.as_str())))
+}
+
+#[derive(Deserialize)]
+pub struct GitHubCallback {
+    code: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubAccessToken {
+    access_token: String,
+}
+
+/// Finishes the GitHub OAuth web flow, opening a dashboard session
+pub async fn github_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(callback): Query<GitHubCallback>,
+) -> Result<(CookieJar, Redirect)> {
+    let (Some(client_id), Some(client_secret)) = (
+        &state.config.github_client_id,
+        &state.config.github_client_secret,
+    ) else {
+        return Err(github_oauth_disabled());
+    };
+
+    if jar.get(OAUTH_STATE_COOKIE).map(|c| c.value()) != Some(callback.state.as_str()) {
+        return Err(Error::BadRequest {
+            message: "OAuth state does not match, try signing in again".to_owned(),
+        });
+    }
+
+    let token: GitHubAccessToken = state
+        .client
+        .post("https://github.com/login/oauth/access_token")
+        .header(ACCEPT, "application/json")
+        .form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("code", callback.code.as_str()),
+        ])
+        .send()
+        .await
+        .context("Error on exchange GitHub code")?
+        .json()
+        .await
+        .context("Error on deserialize GitHub access token")?;
+
+    let user_id = upsert_github_user(&state, &token.access_token).await?;
+    let jar = jar.remove(Cookie::build(OAUTH_STATE_COOKIE).path("/auth/github"));
+    let jar = super::dashboard::start_session(&state, jar, user_id).await?;
+
+    Ok((jar, Redirect::to("/dashboard")))
+}
 
-    Ok(Json(LoginResponse { token }))
+fn github_oauth_disabled() -> Error {
+    Error::NotFound {
+        message: "GitHub sign in is not configured".to_owned(),
+    }
 }
 
 pub fn generate_token() -> String {
@@ -0,0 +1,168 @@
+use crate::{
+    http::{AppState, Error, Result},
+    queries::user::UserId,
+    stats,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+use time::{Duration, OffsetDateTime};
+
+const CHAR_WIDTH: usize = 7;
+const TEXT_PADDING: usize = 10;
+
+/// How long the totals of a user are reused for, badges being embedded in
+/// pages anyone can load.
+pub const TOTALS_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Coding time of a user per project and language.
+pub type Totals = Arc<HashMap<(Option<String>, Option<String>), Duration>>;
+
+#[derive(Deserialize)]
+pub struct BadgeQuery {
+    /// Only count time spent in this language
+    language: Option<String>,
+}
+
+/// Total coding time of a user, e.g. `/badge/octocat.svg`
+pub async fn user_badge(
+    State(state): State<AppState>,
+    Path(file): Path<String>,
+    Query(query): Query<BadgeQuery>,
+) -> Result<Response> {
+    let username = strip_svg(&file)?;
+
+    let user = crate::queries::user::find_by_username(&state.db, username)
+        .await?
+        .filter(|user| user.visibility.public_badges)
+        .ok_or_else(not_found)?;
+
+    let language = query.language.map(|l| l.to_lowercase());
+    let total = total(&state, user.id, |_, l| {
+        language.is_none() || l == language.as_deref()
+    })
+    .await?;
+
+    let label = language.as_deref().unwrap_or("coding time");
+
+    Ok(svg(label, &format_hours(total)))
+}
+
+/// Coding time of a user in one project, e.g. `/badge/octocat/cairos.svg`
+pub async fn project_badge(
+    State(state): State<AppState>,
+    Path((username, file)): Path<(String, String)>,
+    Query(query): Query<BadgeQuery>,
+) -> Result<Response> {
+    let project = strip_svg(&file)?;
+
+    let user = crate::queries::user::find_by_username(&state.db, &username)
+        .await?
+        .ok_or_else(not_found)?;
+
+    if !crate::queries::public_projects::exists(&state.db, user.id, project).await? {
+        return Err(not_found());
+    }
+
+    let language = query.language.map(|l| l.to_lowercase());
+    let total = total(&state, user.id, |p, l| {
+        p == Some(project) && (language.is_none() || l == language.as_deref())
+    })
+    .await?;
+
+    let label = language.as_deref().unwrap_or(project);
+
+    Ok(svg(label, &format_hours(total)))
+}
+
+fn strip_svg(file: &str) -> Result<&str> {
+    file.strip_suffix(".svg").ok_or_else(not_found)
+}
+
+/// Same message whether the user doesn't exist or didn't opt in, so private
+/// accounts can't be discovered through badges.
+fn not_found() -> Error {
+    Error::NotFound {
+        message: String::from("Badge not found"),
+    }
+}
+
+/// Time of the projects and languages kept by `filter`.
+async fn total(
+    state: &AppState,
+    user_id: UserId,
+    filter: impl Fn(Option<&str>, Option<&str>) -> bool,
+) -> Result<Duration> {
+    Ok(totals(state, user_id)
+        .await?
+        .iter()
+        .filter(|((project, language), _)| filter(project.as_deref(), language.as_deref()))
+        .map(|(_, duration)| *duration)
+        .sum())
+}
+
+async fn totals(state: &AppState, user_id: UserId) -> Result<Totals> {
+    if let Some(totals) = state.badge_totals.get(&user_id) {
+        return Ok(totals);
+    }
+
+    let heartbeats = crate::queries::events::list(
+        &state.db,
+        user_id,
+        OffsetDateTime::UNIX_EPOCH,
+        OffsetDateTime::now_utc(),
+    )
+    .await?;
+    let totals: Totals = Arc::new(
+        stats::group_by(&stats::activities(&heartbeats), |h| {
+            (h.project.clone(), h.language.clone())
+        })
+        .into_iter()
+        .collect(),
+    );
+    state.badge_totals.insert(user_id, totals.clone());
+
+    Ok(totals)
+}
+
+fn format_hours(duration: Duration) -> String {
+    match duration.whole_hours() {
+        0 => format!("{} mins", duration.whole_minutes()),
+        1 => String::from("1 hr"),
+        hours => format!("{hours} hrs"),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders a flat, shields.io style badge.
+fn svg(label: &str, value: &str) -> Response {
+    let label_width = label.chars().count() * CHAR_WIDTH + TEXT_PADDING;
+    let value_width = value.chars().count() * CHAR_WIDTH + TEXT_PADDING;
+    let width = label_width + value_width;
+    let label_x = label_width / 2;
+    let value_x = label_width + value_width / 2;
+    let (label, value) = (escape(label), escape(value));
+
+    let body = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {value}"><title>{label}: {value}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{value_width}" height="20" fill="#6d5dfc"/><rect width="{width}" height="20" fill="url(#s)"/></g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text><text x="{label_x}" y="14">{label}</text><text x="{value_x}" y="15" fill="#010101" fill-opacity=".3">{value}</text><text x="{value_x}" y="14">{value}</text></g></svg>"##
+    );
+
+    (
+        [
+            (CONTENT_TYPE, "image/svg+xml"),
+            (CACHE_CONTROL, "public, max-age=3600"),
+        ],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,50 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Number of entries kept before expired ones are pruned, new ones being left
+/// out while none has expired.
+const MAX_ENTRIES: usize = 10_000;
+
+/// Values kept for `ttl`, so public pages requested by anyone don't go
+/// through all the events of a user each time.
+#[derive(Clone)]
+pub struct Cache<K, V> {
+    entries: Arc<Mutex<HashMap<K, (Instant, V)>>>,
+    ttl: Duration,
+}
+
+impl<K: Eq + Hash, V: Clone> Cache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().expect("cache lock poisoned");
+
+        entries
+            .get(key)
+            .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, (stored_at, _)| now.duration_since(*stored_at) < self.ttl);
+            if entries.len() >= MAX_ENTRIES {
+                return;
+            }
+        }
+
+        entries.insert(key, (now, value));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    http::{
+        AppState, Error, Result,
+        error::{ErrorMessage, ValidationErrors},
+        events::{MAX_BRANCH_LENGTH, MAX_CLOCK_SKEW, MAX_EVENT_AGE, optional_text},
+        extractor::{AuthUser, JsonBody},
+    },
+    stats,
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use time::{Duration, OffsetDateTime};
+use utoipa::{IntoParams, ToSchema};
+
+const MAX_PROJECT_LENGTH: usize = 255;
+const MAX_MESSAGE_LENGTH: usize = 1024;
+const MAX_AUTHOR_LENGTH: usize = 255;
+const MAX_FILE_LENGTH: usize = 2048;
+const MAX_FILES: usize = 10_000;
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 365;
+
+/// Furthest back the time of a commit is looked for, when nothing was
+/// committed or checked out in its project before.
+pub(super) const MAX_LOOKBACK: Duration = Duration::days(7);
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitKind {
+    #[default]
+    Commit,
+    /// Another branch was checked out, the time before it belonging to the
+    /// previous one
+    Checkout,
+}
+
+impl CommitKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Commit => "commit",
+            Self::Checkout => "checkout",
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CommitRequest {
+    /// Repository, named like the project of the events in it
+    #[schema(max_length = 255)]
+    project: String,
+    /// Commit made, or checked out
+    #[schema(example = "3f786850e387550fdab836ed7e6dc881de23001b")]
+    sha: String,
+    #[serde(default)]
+    kind: CommitKind,
+    /// `null` on a detached HEAD
+    #[schema(max_length = 255)]
+    branch: Option<String>,
+    /// Subject line of the commit, searched for ticket keys
+    #[schema(max_length = 1024)]
+    message: Option<String>,
+    #[schema(max_length = 255)]
+    author_name: Option<String>,
+    #[schema(max_length = 255)]
+    author_email: Option<String>,
+    /// Paths changed by the commit, relative to the repository
+    #[serde(default)]
+    files: Vec<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    authored_at: Option<OffsetDateTime>,
+    /// When it was committed or checked out, defaults to the time it was
+    /// received
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    timestamp: Option<OffsetDateTime>,
+}
+
+impl CommitRequest {
+    fn validate(mut self, now: OffsetDateTime) -> Result<Self> {
+        let mut errors: Vec<(&'static str, Cow<'static, str>)> = Vec::new();
+
+        self.project = optional_text(
+            &mut errors,
+            "project",
+            Some(self.project),
+            MAX_PROJECT_LENGTH,
+        )
+        .unwrap_or_default();
+        if self.project.is_empty() {
+            errors.push(("project", "must not be empty".into()));
+        }
+
+        self.sha = self.sha.trim().to_lowercase();
+        if !matches!(self.sha.len(), 40 | 64) || !self.sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            errors.push(("sha", "must be a full SHA-1 or SHA-256 object name".into()));
+        }
+
+        self.branch = optional_text(&mut errors, "branch", self.branch, MAX_BRANCH_LENGTH);
+        self.message = optional_text(&mut errors, "message", self.message, MAX_MESSAGE_LENGTH);
+        self.author_name = optional_text(
+            &mut errors,
+            "author_name",
+            self.author_name,
+            MAX_AUTHOR_LENGTH,
+        );
+        self.author_email = optional_text(
+            &mut errors,
+            "author_email",
+            self.author_email,
+            MAX_AUTHOR_LENGTH,
+        );
+
+        if self.files.len() > MAX_FILES {
+            errors.push((
+                "files",
+                format!("must have at most {MAX_FILES} paths").into(),
+            ));
+        }
+        if self
+            .files
+            .iter()
+            .any(|file| file.is_empty() || file.len() > MAX_FILE_LENGTH)
+        {
+            errors.push((
+                "files",
+                format!("must be paths of 1 to {MAX_FILE_LENGTH} bytes").into(),
+            ));
+        }
+        if self
+            .files
+            .iter()
+            .any(|file| file.chars().any(char::is_control))
+        {
+            errors.push(("files", "must not contain control characters".into()));
+        }
+
+        if self.kind == CommitKind::Checkout {
+            if !self.files.is_empty() {
+                errors.push(("files", "must be empty for checkouts".into()));
+            }
+            if self.message.is_some() {
+                errors.push(("message", "is only allowed for commits".into()));
+            }
+        }
+        for (field, time) in [
+            ("authored_at", self.authored_at),
+            ("timestamp", self.timestamp),
+        ] {
+            if time.is_some_and(|time| time > now + MAX_CLOCK_SKEW) {
+                errors.push((field, "must not be in the future".into()));
+            } else if time.is_some_and(|time| time < now - MAX_EVENT_AGE) {
+                errors.push((
+                    field,
+                    format!("must not be older than {} days", MAX_EVENT_AGE.whole_days()).into(),
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::unprocessable_entity(errors));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CommitResponse {
+    success: bool,
+}
+
+/// Records a commit, or a checkout of another branch, sent by the git hooks
+#[utoipa::path(
+    post,
+    path = "/commits",
+    tag = "commits",
+    request_body = CommitRequest,
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Commit recorded", body = CommitResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+        (status = 422, description = "Invalid commit", body = ValidationErrors),
+        (status = 429, description = "Rate limit exceeded", body = ErrorMessage,
+            headers(("Retry-After" = u64, description = "Seconds until a request is accepted again"))),
+    )
+)]
+pub async fn create(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<CommitRequest>,
+) -> Result<Json<CommitResponse>> {
+    let now = OffsetDateTime::now_utc();
+    let payload = payload.validate(now)?;
+
+    crate::queries::commits::create(
+        &state.db,
+        &crate::queries::commits::CreateParams {
+            user_id: auth_user.id,
+            project: payload.project,
+            sha: payload.sha,
+            kind: payload.kind.as_str().to_owned(),
+            branch: payload.branch,
+            message: payload.message,
+            author_name: payload.author_name,
+            author_email: payload.author_email,
+            files: payload.files,
+            authored_at: payload.authored_at,
+            now: payload.timestamp.unwrap_or(now),
+        },
+    )
+    .await?;
+
+    Ok(Json(CommitResponse { success: true }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CommitsQuery {
+    /// Only the commits of this project
+    project: Option<String>,
+    /// Number of UTC days to include, ending today
+    #[param(minimum = 1, maximum = 365, default = 7)]
+    days: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Commit {
+    project: String,
+    sha: String,
+    branch: Option<String>,
+    message: Option<String>,
+    author_name: Option<String>,
+    author_email: Option<String>,
+    files: Vec<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    authored_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    committed_at: OffsetDateTime,
+    /// Time spent in the project since its previous commit or checkout
+    seconds: i64,
+}
+
+/// Commits of the authenticated user, newest first, with the time that went into each
+#[utoipa::path(
+    get,
+    path = "/commits",
+    tag = "commits",
+    params(CommitsQuery),
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Commits with their time", body = Vec<Commit>),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+    )
+)]
+pub async fn list(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<CommitsQuery>,
+) -> Result<Json<Vec<Commit>>> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let now = OffsetDateTime::now_utc();
+    let from = (now - Duration::days(days - 1)).replace_time(time::Time::MIDNIGHT);
+    // The first commits in range take their time from before it.
+    let since = from - MAX_LOOKBACK;
+
+    let commits = crate::queries::commits::list(
+        &state.db,
+        auth_user.id,
+        query.project.as_deref(),
+        since,
+        now,
+    )
+    .await?;
+    let heartbeats = crate::queries::events::list(&state.db, auth_user.id, since, now).await?;
+    let activities = stats::activities(&heartbeats);
+
+    let mut listed = Vec::new();
+    for (i, commit) in commits.iter().enumerate() {
+        if commit.kind != CommitKind::Commit.as_str() || commit.created_at < from {
+            continue;
+        }
+
+        let previous = commits[..i]
+            .iter()
+            .rev()
+            .find(|c| c.project == commit.project)
+            .map(|c| c.created_at);
+        let start = previous
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+            .max(commit.created_at - MAX_LOOKBACK);
+        let seconds =
+            stats::project_between(&activities, &commit.project, start, commit.created_at);
+
+        listed.push(Commit {
+            project: commit.project.clone(),
+            sha: commit.sha.clone(),
+            branch: commit.branch.clone(),
+            message: commit.message.clone(),
+            author_name: commit.author_name.clone(),
+            author_email: commit.author_email.clone(),
+            files: commit.files.clone(),
+            authored_at: commit.authored_at,
+            committed_at: commit.created_at,
+            seconds: seconds.whole_seconds(),
+        });
+    }
+    listed.reverse();
+
+    Ok(Json(listed))
+}
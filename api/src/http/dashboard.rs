@@ -0,0 +1,308 @@
+use crate::{
+    http::{AppState, Result, auth::generate_token, extractor::SessionUser},
+    queries::user::{UserId, Visibility},
+    stats,
+};
+use anyhow::Context;
+use askama::Template;
+use axum::{
+    Form,
+    extract::{Query, State},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    Form as MultiForm,
+    cookie::{Cookie, CookieJar, SameSite},
+};
+use serde::Deserialize;
+use time::{Duration, OffsetDateTime, macros::format_description};
+
+pub const SESSION_COOKIE: &str = "cairos_session";
+const SESSION_LIFETIME: Duration = Duration::days(30);
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 90;
+const TIMELINE_LENGTH: usize = 20;
+
+pub fn render(template: impl Template) -> Result<Html<String>> {
+    Ok(Html(
+        template
+            .render()
+            .context("Error when trying to render template")?,
+    ))
+}
+
+/// Opens a browser session for `user_id`, storing its token in a cookie.
+pub async fn start_session(state: &AppState, jar: CookieJar, user_id: UserId) -> Result<CookieJar> {
+    let now = OffsetDateTime::now_utc();
+    let token = generate_token();
+
+    crate::queries::sessions::delete_expired(&state.db, now).await?;
+    crate::queries::sessions::create(&state.db, user_id, &token, now + SESSION_LIFETIME, now)
+        .await?;
+
+    let cookie = Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .secure(state.config.secure_cookies)
+        .same_site(SameSite::Lax)
+        .max_age(SESSION_LIFETIME);
+
+    Ok(jar.add(cookie))
+}
+
+#[derive(Template)]
+#[template(path = "login.html")]
+struct LoginTemplate {
+    github_enabled: bool,
+    error: Option<&'static str>,
+}
+
+pub async fn login_page(State(state): State<AppState>) -> Result<Html<String>> {
+    render(LoginTemplate {
+        github_enabled: state.config.github_client_id.is_some(),
+        error: None,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct TokenLogin {
+    token: String,
+}
+
+/// Signs in with an API token, for servers without a GitHub OAuth app
+pub async fn login_with_token(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Form(form): Form<TokenLogin>,
+) -> Result<Response> {
+    let Some(user_id) =
+        crate::queries::user::find_user_id_from_token(&state.db, form.token.trim()).await?
+    else {
+        return Ok(render(LoginTemplate {
+            github_enabled: state.config.github_client_id.is_some(),
+            error: Some("Invalid token"),
+        })?
+        .into_response());
+    };
+
+    let jar = start_session(&state, jar, user_id).await?;
+
+    Ok((jar, Redirect::to("/dashboard")).into_response())
+}
+
+pub async fn logout(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Redirect)> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        crate::queries::sessions::delete(&state.db, cookie.value()).await?;
+    }
+
+    Ok((
+        jar.remove(Cookie::build(SESSION_COOKIE).path("/")),
+        Redirect::to("/dashboard/login"),
+    ))
+}
+
+pub struct Bar {
+    pub label: String,
+    pub value: String,
+    pub percent: i64,
+}
+
+struct TimelineEntry {
+    date: String,
+    start: String,
+    end: String,
+    duration: String,
+    project: String,
+}
+
+#[derive(Template)]
+#[template(path = "dashboard.html")]
+struct DashboardTemplate {
+    username: String,
+    days: i64,
+    total: String,
+    churn: stats::Churn,
+    daily: Vec<Bar>,
+    projects: Vec<Bar>,
+    languages: Vec<Bar>,
+    editors: Vec<Bar>,
+    machines: Vec<Bar>,
+    categories: Vec<Bar>,
+    timeline: Vec<TimelineEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct DashboardQuery {
+    days: Option<i64>,
+}
+
+pub async fn index(
+    user: SessionUser,
+    State(state): State<AppState>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Html<String>> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let now = OffsetDateTime::now_utc();
+    let from = (now - Duration::days(days - 1)).replace_time(time::Time::MIDNIGHT);
+
+    let heartbeats = crate::queries::events::list(&state.db, user.id, from, now).await?;
+    let activities = stats::activities(&heartbeats);
+
+    let daily = stats::by_day(&activities, from.date(), now.date())
+        .into_iter()
+        .map(|(day, duration)| {
+            let label = day
+                .format(format_description!("[weekday repr:short] [day]"))
+                .unwrap_or_default();
+            (label, duration)
+        })
+        .collect();
+    let projects = stats::group_by(&activities, |h| {
+        h.project.clone().unwrap_or_else(|| "Unknown".to_owned())
+    });
+    let languages = stats::group_by(&activities, |h| {
+        h.language.clone().unwrap_or_else(|| "Unknown".to_owned())
+    });
+    let editors = stats::group_by(&activities, |h| {
+        h.editor.clone().unwrap_or_else(|| "Unknown".to_owned())
+    });
+    let categories = stats::group_by(&activities, |h| {
+        h.category.clone().unwrap_or_else(|| "Unknown".to_owned())
+    });
+    let hostnames = stats::hostnames(&heartbeats);
+    let machines = stats::group_by(&activities, |h| h.machine_id.clone())
+        .into_iter()
+        .map(|(id, duration)| {
+            let label = id
+                .map(|id| hostnames.get(&id).cloned().unwrap_or(id))
+                .unwrap_or_else(|| "Unknown".to_owned());
+            (label, duration)
+        })
+        .collect();
+
+    let time_format = format_description!("[hour]:[minute]");
+    let timeline = stats::sessions(&activities)
+        .into_iter()
+        .take(TIMELINE_LENGTH)
+        .map(|session| TimelineEntry {
+            date: session.start.date().to_string(),
+            start: session.start.format(time_format).unwrap_or_default(),
+            end: session.end.format(time_format).unwrap_or_default(),
+            duration: stats::format_duration(session.end - session.start),
+            project: session.project.unwrap_or_else(|| "Unknown".to_owned()),
+        })
+        .collect();
+
+    render(DashboardTemplate {
+        username: user.username,
+        days,
+        total: stats::format_duration(stats::total(&activities)),
+        churn: stats::churn(&activities),
+        daily: bars(daily),
+        projects: bars(projects),
+        languages: bars(languages),
+        editors: bars(editors),
+        machines: bars(machines),
+        categories: bars(categories),
+        timeline,
+    })
+}
+
+/// Turns durations into bars sized relative to the longest one.
+pub fn bars(items: Vec<(String, Duration)>) -> Vec<Bar> {
+    let longest = items
+        .iter()
+        .map(|(_, duration)| duration.whole_seconds())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    items
+        .into_iter()
+        .map(|(label, duration)| Bar {
+            label,
+            value: stats::format_duration(duration),
+            percent: duration.whole_seconds() * 100 / longest,
+        })
+        .collect()
+}
+
+struct ProjectSetting {
+    name: String,
+    public: bool,
+}
+
+#[derive(Template)]
+#[template(path = "settings.html")]
+struct SettingsTemplate {
+    username: String,
+    visibility: Visibility,
+    projects: Vec<ProjectSetting>,
+}
+
+pub async fn settings(user: SessionUser, State(state): State<AppState>) -> Result<Html<String>> {
+    let projects = crate::queries::events::list_projects(&state.db, user.id).await?;
+    let public = crate::queries::public_projects::list(&state.db, user.id).await?;
+
+    render(SettingsTemplate {
+        username: user.username,
+        visibility: user.visibility,
+        projects: projects
+            .into_iter()
+            .map(|name| ProjectSetting {
+                public: public.contains(&name),
+                name,
+            })
+            .collect(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct SettingsForm {
+    /// Checkboxes, only sent when checked
+    public_badges: Option<String>,
+    public_profile: Option<String>,
+    profile_languages: Option<String>,
+    profile_weekly_totals: Option<String>,
+    profile_streaks: Option<String>,
+    profile_projects: Option<String>,
+    #[serde(default)]
+    projects: Vec<String>,
+}
+
+pub async fn update_settings(
+    user: SessionUser,
+    State(state): State<AppState>,
+    MultiForm(form): MultiForm<SettingsForm>,
+) -> Result<Redirect> {
+    let known = crate::queries::events::list_projects(&state.db, user.id).await?;
+    let projects: Vec<String> = form
+        .projects
+        .into_iter()
+        .filter(|project| known.contains(project))
+        .collect();
+
+    let visibility = Visibility {
+        public_badges: form.public_badges.is_some(),
+        public_profile: form.public_profile.is_some(),
+        profile_languages: form.profile_languages.is_some(),
+        profile_weekly_totals: form.profile_weekly_totals.is_some(),
+        profile_streaks: form.profile_streaks.is_some(),
+        profile_projects: form.profile_projects.is_some(),
+    };
+
+    crate::queries::user::set_visibility(&state.db, user.id, &visibility).await?;
+    crate::queries::public_projects::replace(
+        &state.db,
+        user.id,
+        &projects,
+        OffsetDateTime::now_utc(),
+    )
+    .await?;
+
+    Ok(Redirect::to("/dashboard/settings"))
+}
@@ -1,15 +1,29 @@
 #![allow(dead_code)]
-use anyhow;
 use axum::Json;
 use axum::http::StatusCode;
-use axum::http::header::WWW_AUTHENTICATE;
+use axum::http::header::{RETRY_AFTER, WWW_AUTHENTICATE};
 use axum::response::{IntoResponse, Response};
-use serde_json::json;
+use serde::Serialize;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Body of error responses carrying a single message
+#[derive(Serialize, ToSchema)]
+pub struct ErrorMessage {
+    pub message: String,
+}
+
+/// Body of `422 Unprocessable Entity` responses, mapping each field to its problems
+#[derive(Serialize, ToSchema)]
+pub struct ValidationErrors {
+    #[schema(value_type = HashMap<String, Vec<String>>)]
+    pub errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
+}
 
 /// This enum maps HTTP codes
-#[derive(thiserror::Error, Debug, Default)]
+#[allow(clippy::enum_variant_names)]
+#[derive(thiserror::Error, Debug)]
 pub enum Error {
     /// Return `400 Bad Request`
     #[error("{message}")]
@@ -37,10 +51,17 @@ pub enum Error {
         errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
     },
 
-    /// Return `500 Internal Server Error`
-    #[default]
+    /// Return `429 Too Many Requests`
+    #[error("too many requests, retry after {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
+
+    /// Return `503 Service Unavailable`
+    #[error("service unavailable: {message}")]
+    ServiceUnavailable { message: String },
+
+    /// Return `500 Internal Server Error`, keeping the cause for the logs
     #[error("an internal server error occurred")]
-    InternalServerError,
+    InternalServerError { source: anyhow::Error },
 }
 
 impl Error {
@@ -69,7 +90,9 @@ impl Error {
             Self::NotFound { .. } => StatusCode::NOT_FOUND,
             Self::Conflict { .. } => StatusCode::CONFLICT,
             Self::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
-            Self::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::ServiceUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::InternalServerError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -77,27 +100,23 @@ impl Error {
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         match &self {
-            Self::BadRequest { message } => {
-                return (self.status_code(), Json(json!({ "message": message }))).into_response();
-            }
-
-            Self::NotFound { message } => {
-                return (self.status_code(), Json(json!({ "message": message }))).into_response();
-            }
-
-            Self::Conflict { message } => {
-                return (self.status_code(), Json(json!({ "message": message }))).into_response();
+            Self::BadRequest { message }
+            | Self::NotFound { message }
+            | Self::Conflict { message }
+            | Self::ServiceUnavailable { message } => {
+                return (
+                    self.status_code(),
+                    Json(ErrorMessage {
+                        message: message.to_owned(),
+                    }),
+                )
+                    .into_response();
             }
 
             Self::UnprocessableEntity { errors } => {
-                #[derive(serde::Serialize)]
-                struct Errors {
-                    errors: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
-                }
-
                 return (
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                    Json(Errors {
+                    self.status_code(),
+                    Json(ValidationErrors {
                         errors: errors.to_owned(),
                     }),
                 )
@@ -108,20 +127,39 @@ impl IntoResponse for Error {
                 return (
                     self.status_code(),
                     [(WWW_AUTHENTICATE, "Token")],
-                    Json(json!({ "message": message })),
+                    Json(ErrorMessage {
+                        message: message.to_owned(),
+                    }),
+                )
+                    .into_response();
+            }
+
+            Self::TooManyRequests { retry_after } => {
+                return (
+                    self.status_code(),
+                    [(RETRY_AFTER, retry_after.to_string())],
+                    Json(ErrorMessage {
+                        message: self.to_string(),
+                    }),
                 )
                     .into_response();
             }
 
-            Self::InternalServerError => {
-                log::error!("Internal Server Error");
+            Self::InternalServerError { source } => {
+                tracing::error!(error = format!("{source:#}"), "internal server error");
             }
 
             // Other errors get mapped normally.
             _ => (),
         }
 
-        (self.status_code(), self.to_string()).into_response()
+        (
+            self.status_code(),
+            Json(ErrorMessage {
+                message: self.to_string(),
+            }),
+        )
+            .into_response()
     }
 }
 
@@ -133,9 +171,7 @@ impl From<Error> for String {
 
 impl From<anyhow::Error> for Error {
     fn from(error: anyhow::Error) -> Self {
-        match error {
-            _ => Self::InternalServerError,
-        }
+        Self::InternalServerError { source: error }
     }
 }
 
@@ -145,7 +181,9 @@ impl From<sqlx::Error> for Error {
             sqlx::Error::RowNotFound => Self::NotFound {
                 message: String::from("Item not found!"),
             },
-            _ => Self::InternalServerError,
+            error => Self::InternalServerError {
+                source: error.into(),
+            },
         }
     }
 }
@@ -1,26 +1,382 @@
-use crate::http::{AppState, Result, extractor::AuthUser};
+use crate::{
+    http::{
+        AppState, Error, Result,
+        error::{ErrorMessage, ValidationErrors},
+        extractor::{AuthUser, JsonBody},
+    },
+    webhooks::Event,
+};
 use axum::{Json, extract::State};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::borrow::Cow;
+use time::{Duration, OffsetDateTime};
+use utoipa::ToSchema;
 
-#[derive(Deserialize)]
+const MAX_URI_LENGTH: usize = 2048;
+const MAX_LANGUAGE_LENGTH: usize = 64;
+const MAX_PROJECT_LENGTH: usize = 255;
+const MAX_SOURCE_LENGTH: usize = 64;
+const MAX_HOSTNAME_LENGTH: usize = 255;
+const MAX_COMMAND_LENGTH: usize = 1024;
+pub(super) const MAX_BRANCH_LENGTH: usize = 255;
+
+/// Kinds of work an event can be categorized as.
+const CATEGORIES: &[&str] = &["coding", "debugging", "docs", "tests", "config"];
+
+/// How far in the future a client clock may be before its events are refused.
+pub(super) const MAX_CLOCK_SKEW: Duration = Duration::minutes(5);
+
+/// Oldest event accepted, so queued events can still be delivered late.
+pub(super) const MAX_EVENT_AGE: Duration = Duration::days(7);
+
+/// Longest a terminal command is taken to have run, longer ones being cut.
+const MAX_COMMAND_DURATION: Duration = Duration::hours(12);
+
+/// Where the activity happened.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A file was opened, edited or saved
+    #[default]
+    Editor,
+    /// A command ran in a shell, `uri` being its working directory
+    Terminal,
+}
+
+impl EventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Editor => "editor",
+            Self::Terminal => "terminal",
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct CaptureRequest {
+    /// Path of the file the activity happened in
+    #[schema(max_length = 2048)]
     uri: String,
+    /// Whether the file was saved
     is_write: bool,
+    /// Language identifier, normalized to lowercase
+    #[schema(max_length = 64)]
     language: Option<String>,
+    #[schema(minimum = 0)]
     line_number: Option<i32>,
+    #[schema(minimum = 0)]
     cursor_pos: Option<i32>,
+    /// Name of the workspace the file belongs to
+    #[schema(max_length = 255)]
+    project: Option<String>,
+    /// When the activity happened, defaults to the time it was received
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    timestamp: Option<OffsetDateTime>,
+    /// Editor name, as reported by the LSP client
+    #[schema(max_length = 64)]
+    editor: Option<String>,
+    #[schema(max_length = 64)]
+    editor_version: Option<String>,
+    /// Version of the `cairos` CLI
+    #[schema(max_length = 64)]
+    client_version: Option<String>,
+    #[schema(max_length = 64)]
+    os: Option<String>,
+    #[schema(max_length = 64)]
+    arch: Option<String>,
+    /// Random ID of the machine, stable across hostname changes
+    #[schema(max_length = 64)]
+    machine_id: Option<String>,
+    #[schema(max_length = 255)]
+    hostname: Option<String>,
+    /// Lines added to the file since its previous event
+    #[serde(default)]
+    #[schema(minimum = 0)]
+    lines_added: i32,
+    /// Lines removed from the file since its previous event
+    #[serde(default)]
+    #[schema(minimum = 0)]
+    lines_removed: i32,
+    /// Non-whitespace characters typed since the previous event
+    #[serde(default)]
+    #[schema(minimum = 0)]
+    typed_chars: i32,
+    /// Non-whitespace characters pasted since the previous event
+    #[serde(default)]
+    #[schema(minimum = 0)]
+    pasted_chars: i32,
+    /// Non-whitespace characters inserted in bulk, e.g. by an AI assistant
+    #[serde(default)]
+    #[schema(minimum = 0)]
+    bulk_chars: i32,
+    /// Kind of work, normalized to lowercase
+    #[schema(example = "tests")]
+    category: Option<String>,
+    #[serde(default)]
+    kind: EventKind,
+    /// Command run, for terminal events, usually without its arguments
+    #[schema(max_length = 1024, example = "cargo")]
+    command: Option<String>,
+    /// How long the terminal command ran, cut to 12 hours and to the time
+    /// since `timestamp` when given
+    #[schema(minimum = 0)]
+    duration_seconds: Option<i32>,
+    /// Git branch checked out, searched for ticket keys
+    #[schema(max_length = 255, example = "feature/PROJ-123-login")]
+    branch: Option<String>,
+}
+
+impl CaptureRequest {
+    /// Checks every field, returning all the problems found at once and
+    /// normalizing the ones that are accepted.
+    fn validate(mut self, now: OffsetDateTime) -> Result<Self> {
+        let mut errors: Vec<(&'static str, Cow<'static, str>)> = Vec::new();
+
+        self.uri =
+            optional_text(&mut errors, "uri", Some(self.uri), MAX_URI_LENGTH).unwrap_or_default();
+        if self.uri.is_empty() {
+            errors.push(("uri", "must not be empty".into()));
+        }
+        self.language = optional_text(
+            &mut errors,
+            "language",
+            self.language.map(|language| language.to_lowercase()),
+            MAX_LANGUAGE_LENGTH,
+        );
+        self.project = optional_text(&mut errors, "project", self.project, MAX_PROJECT_LENGTH);
+
+        if self.line_number.is_some_and(|line| line < 0) {
+            errors.push(("line_number", "must not be negative".into()));
+        }
+        if self.cursor_pos.is_some_and(|pos| pos < 0) {
+            errors.push(("cursor_pos", "must not be negative".into()));
+        }
+        for (field, count) in [
+            ("lines_added", self.lines_added),
+            ("lines_removed", self.lines_removed),
+            ("typed_chars", self.typed_chars),
+            ("pasted_chars", self.pasted_chars),
+            ("bulk_chars", self.bulk_chars),
+        ] {
+            if count < 0 {
+                errors.push((field, "must not be negative".into()));
+            }
+        }
+
+        if let Some(timestamp) = self.timestamp {
+            if timestamp > now + MAX_CLOCK_SKEW {
+                errors.push(("timestamp", "must not be in the future".into()));
+            } else if timestamp < now - MAX_EVENT_AGE {
+                errors.push((
+                    "timestamp",
+                    format!("must not be older than {} days", MAX_EVENT_AGE.whole_days()).into(),
+                ));
+            }
+        }
+
+        self.editor = optional_text(&mut errors, "editor", self.editor, MAX_SOURCE_LENGTH);
+        self.editor_version = optional_text(
+            &mut errors,
+            "editor_version",
+            self.editor_version,
+            MAX_SOURCE_LENGTH,
+        );
+        self.client_version = optional_text(
+            &mut errors,
+            "client_version",
+            self.client_version,
+            MAX_SOURCE_LENGTH,
+        );
+        self.os = optional_text(&mut errors, "os", self.os, MAX_SOURCE_LENGTH);
+        self.arch = optional_text(&mut errors, "arch", self.arch, MAX_SOURCE_LENGTH);
+        self.machine_id = optional_text(
+            &mut errors,
+            "machine_id",
+            self.machine_id,
+            MAX_SOURCE_LENGTH,
+        );
+        self.hostname = optional_text(&mut errors, "hostname", self.hostname, MAX_HOSTNAME_LENGTH);
+
+        self.category = self
+            .category
+            .map(|category| category.trim().to_lowercase())
+            .filter(|category| !category.is_empty());
+        if let Some(category) = &self.category
+            && !CATEGORIES.contains(&category.as_str())
+        {
+            errors.push((
+                "category",
+                format!("must be one of {}", CATEGORIES.join(", ")).into(),
+            ));
+        }
+
+        self.command = optional_text(&mut errors, "command", self.command, MAX_COMMAND_LENGTH);
+        self.branch = optional_text(&mut errors, "branch", self.branch, MAX_BRANCH_LENGTH);
+        if self.duration_seconds.is_some_and(|seconds| seconds < 0) {
+            errors.push(("duration_seconds", "must not be negative".into()));
+        }
+        match self.kind {
+            EventKind::Terminal if self.command.is_none() => {
+                errors.push(("command", "is required for terminal events".into()));
+            }
+            EventKind::Editor => {
+                if self.command.is_some() {
+                    errors.push(("command", "is only allowed for terminal events".into()));
+                }
+                if self.duration_seconds.is_some() {
+                    errors.push((
+                        "duration_seconds",
+                        "is only allowed for terminal events".into(),
+                    ));
+                }
+            }
+            EventKind::Terminal => {}
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::unprocessable_entity(errors));
+        }
+
+        // `timestamp` is when the command started, it can't have run longer
+        // than since then.
+        let longest = match self.timestamp {
+            Some(started_at) => (now - started_at).clamp(Duration::ZERO, MAX_COMMAND_DURATION),
+            None => MAX_COMMAND_DURATION,
+        };
+        self.duration_seconds = self
+            .duration_seconds
+            .map(|seconds| seconds.min(longest.whole_seconds() as i32));
+
+        Ok(self)
+    }
+}
+
+/// Trims a free text field, dropping it when empty and recording a problem
+/// when it is too long or has control characters.
+pub(super) fn optional_text(
+    errors: &mut Vec<(&'static str, Cow<'static, str>)>,
+    field: &'static str,
+    value: Option<String>,
+    max_length: usize,
+) -> Option<String> {
+    let value = value
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())?;
+
+    if value.len() > max_length {
+        errors.push((field, format!("must be at most {max_length} bytes").into()));
+    }
+    if value.chars().any(char::is_control) {
+        errors.push((field, "must not contain control characters".into()));
+    }
+
+    Some(value)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RenameRequest {
+    /// Previous path of the file or folder
+    #[schema(max_length = 2048)]
+    from: String,
+    /// New path of the file or folder
+    #[schema(max_length = 2048)]
+    to: String,
+}
+
+impl RenameRequest {
+    fn validate(mut self) -> Result<Self> {
+        let mut errors: Vec<(&'static str, Cow<'static, str>)> = Vec::new();
+
+        for (field, path) in [("from", &mut self.from), ("to", &mut self.to)] {
+            *path = path.trim().trim_end_matches('/').to_owned();
+            if path.is_empty() {
+                errors.push((field, "must not be empty".into()));
+            } else if path.len() > MAX_URI_LENGTH {
+                errors.push((
+                    field,
+                    format!("must be at most {MAX_URI_LENGTH} bytes").into(),
+                ));
+            }
+            if path.chars().any(char::is_control) {
+                errors.push((field, "must not contain control characters".into()));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::unprocessable_entity(errors));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RenameResponse {
+    /// Events moved to the new path
+    renamed: u64,
 }
 
-#[derive(Serialize)]
+/// Moves the history of a renamed file or folder to its new path
+#[utoipa::path(
+    post,
+    path = "/events/rename",
+    tag = "events",
+    request_body = RenameRequest,
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "History moved", body = RenameResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+        (status = 422, description = "Invalid paths", body = ValidationErrors),
+        (status = 429, description = "Rate limit exceeded", body = ErrorMessage,
+            headers(("Retry-After" = u64, description = "Seconds until a request is accepted again"))),
+    )
+)]
+pub async fn rename(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<RenameRequest>,
+) -> Result<Json<RenameResponse>> {
+    let payload = payload.validate()?;
+
+    let renamed = if payload.from == payload.to {
+        0
+    } else {
+        crate::queries::events::rename(&state.db, auth_user.id, &payload.from, &payload.to).await?
+    };
+
+    Ok(Json(RenameResponse { renamed }))
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct CaptureResponse {
     success: bool,
 }
 
+/// Records an activity event for the authenticated user
+#[utoipa::path(
+    post,
+    path = "/events/capture",
+    tag = "events",
+    request_body = CaptureRequest,
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Event recorded", body = CaptureResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+        (status = 422, description = "Invalid event payload", body = ValidationErrors),
+        (status = 429, description = "Rate limit exceeded", body = ErrorMessage,
+            headers(("Retry-After" = u64, description = "Seconds until a request is accepted again"))),
+    )
+)]
 pub async fn capture(
     auth_user: AuthUser,
     State(state): State<AppState>,
-    Json(payload): Json<CaptureRequest>,
+    JsonBody(payload): JsonBody<CaptureRequest>,
 ) -> Result<Json<CaptureResponse>> {
+    let now = OffsetDateTime::now_utc();
+    let payload = payload.validate(now)?;
+    let created_at = payload.timestamp.unwrap_or(now);
+
     crate::queries::events::create(
         &state.db,
         &crate::queries::events::CreateParams {
@@ -29,11 +385,37 @@ pub async fn capture(
             language: payload.language,
             line_number: payload.line_number,
             cursor_pos: payload.cursor_pos,
+            project: payload.project,
+            editor: payload.editor,
+            editor_version: payload.editor_version,
+            client_version: payload.client_version,
+            os: payload.os,
+            arch: payload.arch,
+            machine_id: payload.machine_id,
+            hostname: payload.hostname,
+            lines_added: payload.lines_added,
+            lines_removed: payload.lines_removed,
+            typed_chars: payload.typed_chars,
+            pasted_chars: payload.pasted_chars,
+            bulk_chars: payload.bulk_chars,
+            category: payload.category,
+            kind: payload.kind.as_str().to_owned(),
+            command: payload.command,
+            duration_seconds: payload.duration_seconds,
+            branch: payload.branch,
             user_id: auth_user.id,
-            now: time::OffsetDateTime::now_utc(),
+            now: created_at,
         },
     )
     .await?;
 
+    let day = created_at.date();
+    if crate::queries::events::claim_day(&state.db, auth_user.id, day).await? {
+        let data = json!({ "day": day.to_string() });
+        crate::webhooks::enqueue(&state.db, auth_user.id, Event::FirstEventOfDay, data).await;
+    }
+
+    metrics::counter!("events_ingested_total").increment(1);
+
     Ok(Json(CaptureResponse { success: true }))
 }
@@ -1,15 +1,21 @@
-use super::Error;
-use crate::{http::AppState, queries::user::UserId};
+use super::{Error, dashboard::SESSION_COOKIE};
+use crate::{
+    http::AppState,
+    queries::user::{UserId, Visibility},
+};
 
 use axum::{
-    RequestPartsExt,
-    extract::{FromRef, FromRequestParts},
+    Json, RequestPartsExt,
+    extract::{FromRef, FromRequest, FromRequestParts, Request, rejection::JsonRejection},
     http::request::Parts,
+    response::{IntoResponse, Redirect, Response},
 };
 use axum_extra::{
     TypedHeader,
+    extract::cookie::CookieJar,
     headers::{Authorization, authorization::Bearer},
 };
+use serde::de::DeserializeOwned;
 
 pub struct AuthUser {
     pub id: UserId,
@@ -36,6 +42,11 @@ where
     type Rejection = Error;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Already validated by the rate limiter in front of the route.
+        if let Some(&id) = parts.extensions.get::<UserId>() {
+            return Ok(Self { id });
+        }
+
         let app_state: AppState = AppState::from_ref(state);
 
         let authorization = parts
@@ -48,3 +59,93 @@ where
         Self::from_authorization(&app_state, authorization.token()).await
     }
 }
+
+/// `Json` body answering a mistyped or missing field with the same `422` as
+/// the checks of `validate`, instead of a plain text error.
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(JsonRejection::JsonDataError(rejection)) => {
+                Err(Error::unprocessable_entity([field_error(&rejection)]).into_response())
+            }
+            Err(rejection) => Err(rejection.into_response()),
+        }
+    }
+}
+
+/// Field of the body that failed to deserialize, `body` when it can't be
+/// told, and the problem without its position.
+fn field_error(rejection: &dyn std::error::Error) -> (String, String) {
+    let mut source = rejection.source();
+    while let Some(error) = source {
+        if let Some(error) = error.downcast_ref::<serde_path_to_error::Error<serde_json::Error>>() {
+            let message = error.inner().to_string();
+            let message = message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(message, _)| message);
+            let path = error.path().to_string();
+
+            return match message.strip_prefix("missing field `") {
+                Some(field) if path == "." => (
+                    field.trim_end_matches('`').to_owned(),
+                    "is required".to_owned(),
+                ),
+                _ if path == "." => ("body".to_owned(), message.to_owned()),
+                _ => (path, message.to_owned()),
+            };
+        }
+        source = error.source();
+    }
+
+    ("body".to_owned(), rejection.to_string())
+}
+
+/// User signed in to the dashboard through a session cookie.
+pub struct SessionUser {
+    pub id: UserId,
+    pub username: String,
+    pub visibility: Visibility,
+}
+
+impl<S> FromRequestParts<S> for SessionUser
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let app_state: AppState = AppState::from_ref(state);
+        let jar = CookieJar::from_headers(&parts.headers);
+
+        let Some(token) = jar.get(SESSION_COOKIE).map(|c| c.value().to_owned()) else {
+            return Err(Redirect::to("/dashboard/login").into_response());
+        };
+
+        let user = crate::queries::user::find_user_from_session(
+            &app_state.db,
+            &token,
+            time::OffsetDateTime::now_utc(),
+        )
+        .await
+        .map_err(|e| Error::from(e).into_response())?;
+
+        match user {
+            Some(user) => Ok(Self {
+                id: user.id,
+                username: user.username,
+                visibility: user.visibility,
+            }),
+            None => Err(Redirect::to("/dashboard/login").into_response()),
+        }
+    }
+}
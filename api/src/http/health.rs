@@ -0,0 +1,19 @@
+use crate::http::{AppState, Error, Result};
+use axum::extract::State;
+
+/// Liveness probe, answering as long as the process serves requests
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Readiness probe, answering only when the database is reachable
+pub async fn readyz(State(state): State<AppState>) -> Result<&'static str> {
+    crate::queries::ping(&state.db).await.map_err(|e| {
+        tracing::warn!(error = %e, "readiness check failed");
+        Error::ServiceUnavailable {
+            message: String::from("database is unreachable"),
+        }
+    })?;
+
+    Ok("ok")
+}
@@ -1,22 +1,52 @@
-use crate::config::Config;
+use crate::{
+    config::{BindAddress, Config},
+    queries::user::{UserId, Visibility},
+};
 use anyhow::Context;
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    handler::Handler,
+    http::{HeaderValue, Method, header},
+    middleware,
+    routing::{delete, get, post},
+};
+use cache::Cache;
 use error::Error;
+use metrics_exporter_prometheus::PrometheusHandle;
+use openapi::ApiDoc;
+use rate_limit::RateLimiter;
 use sqlx::PgPool;
-use std::{
-    net::{Ipv4Addr, SocketAddr},
-    time::Duration,
-};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 use tower_http::{
-    catch_panic::CatchPanicLayer, compression::CompressionLayer, timeout::TimeoutLayer,
-    trace::TraceLayer,
+    catch_panic::CatchPanicLayer,
+    compression::CompressionLayer,
+    cors::{AllowOrigin, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    timeout::TimeoutLayer,
+    trace::{DefaultOnResponse, TraceLayer},
 };
+use tracing::Level;
+use utoipa::OpenApi;
+use utoipa_scalar::{Scalar, Servable};
 
 mod auth;
+mod badges;
+mod cache;
+mod commits;
+mod dashboard;
 mod error;
 mod events;
 mod extractor;
+mod health;
+mod metrics;
+mod openapi;
+mod profiles;
+mod rate_limit;
+mod reports;
+mod stats;
+mod tls;
+mod webhooks;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -24,6 +54,10 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub struct AppState {
     pub db: sqlx::PgPool,
     pub client: reqwest::Client,
+    pub metrics: PrometheusHandle,
+    pub config: Arc<Config>,
+    pub badge_totals: Cache<UserId, badges::Totals>,
+    pub profiles: Cache<(UserId, Visibility), profiles::Profile>,
 }
 
 pub async fn serve(config: Config, db: PgPool) -> anyhow::Result<()> {
@@ -33,31 +67,202 @@ pub async fn serve(config: Config, db: PgPool) -> anyhow::Result<()> {
             .user_agent("CAIROS/1.0.0")
             .build()
             .expect("Error on build Client."),
+        metrics: metrics::install()?,
+        config: Arc::new(config.clone()),
+        badge_totals: Cache::new(badges::TOTALS_TTL),
+        profiles: Cache::new(profiles::PROFILE_TTL),
     };
 
-    let addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, config.port));
-    let listener = TcpListener::bind(addr).await?;
-    let app = app_router(app_state);
+    tokio::spawn(crate::webhooks::run(app_state.db.clone()));
+
+    if let Some(addr) = config.metrics_bind {
+        let listener = TcpListener::bind(addr)
+            .await
+            .context("Error when trying to bind the metrics address")?;
+        let metrics_app = Router::new()
+            .route("/metrics", get(metrics::render))
+            .with_state(app_state.clone());
+
+        tokio::spawn(async move {
+            let served = axum::serve(listener, metrics_app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await;
+            if let Err(e) = served {
+                tracing::error!(error = %e, "metrics server stopped");
+            }
+        });
+    }
+
+    let app = app_router(&config, app_state);
+    let tls = config.tls_cert.as_deref().zip(config.tls_key.as_deref());
+
+    match (config.bind_address(), tls) {
+        (BindAddress::Tcp(addr), Some((cert, key))) => {
+            let reload_interval = Duration::from_secs(config.tls_reload_interval);
+            tls::serve(addr, cert, key, reload_interval, app).await
+        }
+        (BindAddress::Tcp(addr), None) => {
+            let listener = TcpListener::bind(addr).await?;
+
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(shutdown_signal())
+            .await
+            .context("Error when trying to run HTTP server")
+        }
+        #[cfg(unix)]
+        (BindAddress::Unix(path), None) => {
+            // A socket left behind by a previous run would make bind fail.
+            if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+                use std::os::unix::fs::FileTypeExt;
+                if !metadata.file_type().is_socket() {
+                    anyhow::bail!("{} exists and is not a socket", path.display());
+                }
+                std::fs::remove_file(&path).context("Error when trying to remove old socket")?;
+            }
+            let listener = tokio::net::UnixListener::bind(&path)?;
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .context("Error when trying to run HTTP server")
+            axum::serve(listener, app.into_make_service())
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+                .context("Error when trying to run HTTP server")
+        }
+        #[cfg(unix)]
+        (BindAddress::Unix(_), Some(_)) => {
+            anyhow::bail!("TLS is not supported on Unix sockets, terminate it in the proxy")
+        }
+        #[cfg(not(unix))]
+        (BindAddress::Unix(_), _) => anyhow::bail!("Unix sockets are not supported here"),
+    }
 }
 
-fn app_router(app_state: AppState) -> Router {
+fn app_router(config: &Config, app_state: AppState) -> Router {
+    let capture_limiter = RateLimiter::new(config.capture_rate_limit, &config.trusted_proxies);
+    let login_limiter = RateLimiter::new(config.login_rate_limit, &config.trusted_proxies);
+    let public_limiter = RateLimiter::new(config.public_rate_limit, &config.trusted_proxies);
+
     Router::new()
-        .route("/events/capture", post(events::capture))
-        .route("/auth/login", post(auth::login))
+        .route(
+            "/events/capture",
+            post(events::capture).layer(middleware::from_fn_with_state(
+                (capture_limiter.clone(), app_state.clone()),
+                rate_limit::limit_by_user,
+            )),
+        )
+        .route(
+            "/events/rename",
+            post(events::rename).layer(middleware::from_fn_with_state(
+                (capture_limiter.clone(), app_state.clone()),
+                rate_limit::limit_by_user,
+            )),
+        )
+        .route(
+            "/commits",
+            get(commits::list).post(commits::create.layer(middleware::from_fn_with_state(
+                (capture_limiter, app_state.clone()),
+                rate_limit::limit_by_user,
+            ))),
+        )
+        .route(
+            "/auth/login",
+            post(auth::login).layer(middleware::from_fn_with_state(
+                login_limiter.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route("/auth/logout", post(auth::logout))
+        .route("/auth/github", get(auth::github_authorize))
+        .route("/auth/github/callback", get(auth::github_callback))
+        .route("/dashboard", get(dashboard::index))
+        .route(
+            "/dashboard/login",
+            get(dashboard::login_page).post(dashboard::login_with_token.layer(
+                middleware::from_fn_with_state(login_limiter, rate_limit::limit),
+            )),
+        )
+        .route("/dashboard/logout", post(dashboard::logout))
+        .route(
+            "/dashboard/settings",
+            get(dashboard::settings).post(dashboard::update_settings),
+        )
+        .route(
+            "/badge/{file}",
+            get(badges::user_badge).layer(middleware::from_fn_with_state(
+                public_limiter.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route(
+            "/badge/{username}/{file}",
+            get(badges::project_badge).layer(middleware::from_fn_with_state(
+                public_limiter.clone(),
+                rate_limit::limit,
+            )),
+        )
+        .route("/stats", get(stats::show))
+        .route("/reports/tickets", get(reports::tickets))
+        .route("/webhooks", get(webhooks::list).post(webhooks::create))
+        .route("/webhooks/{id}", delete(webhooks::delete))
+        .route("/webhooks/{id}/deliveries", get(webhooks::deliveries))
+        .route(
+            "/u/{file}",
+            get(profiles::show).layer(middleware::from_fn_with_state(
+                public_limiter,
+                rate_limit::limit,
+            )),
+        )
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/openapi.json", get(openapi::spec))
+        .route_layer(middleware::from_fn(metrics::track))
+        .merge(Scalar::with_url("/docs", ApiDoc::openapi()))
+        .layer(cors_layer(&config.cors_origins))
         .layer((
             CompressionLayer::new(),
-            TraceLayer::new_for_http().on_failure(()),
+            SetRequestIdLayer::x_request_id(MakeRequestUuid),
+            TraceLayer::new_for_http()
+                .make_span_with(make_request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO))
+                .on_failure(()),
+            PropagateRequestIdLayer::x_request_id(),
             TimeoutLayer::new(Duration::from_secs(30)),
             CatchPanicLayer::new(),
         ))
         .with_state(app_state)
 }
 
+fn cors_layer(origins: &[HeaderValue]) -> CorsLayer {
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(origins.iter().cloned())
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+        .expose_headers([header::RETRY_AFTER])
+}
+
+/// Span wrapping every request, so all of its logs carry the request ID.
+fn make_request_span(request: &axum::extract::Request) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+    )
+}
+
 async fn shutdown_signal() {
     use tokio::signal;
     let ctrl_c = async {
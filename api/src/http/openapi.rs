@@ -0,0 +1,53 @@
+use axum::Json;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Cairos API", description = "Coding activity tracking"),
+    paths(
+        super::events::capture,
+        super::events::rename,
+        super::commits::create,
+        super::commits::list,
+        super::auth::login,
+        super::auth::logout,
+        super::profiles::show,
+        super::stats::show,
+        super::reports::tickets,
+        super::webhooks::create,
+        super::webhooks::list,
+        super::webhooks::delete,
+        super::webhooks::deliveries,
+    ),
+    modifiers(&TokenSecurity),
+    tags(
+        (name = "events", description = "Activity ingestion"),
+        (name = "commits", description = "Commits and the time spent on them"),
+        (name = "auth", description = "Authentication"),
+        (name = "profiles", description = "Public profiles"),
+        (name = "stats", description = "Coding time reports"),
+        (name = "webhooks", description = "Signed notifications sent to your own endpoints"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Registers the bearer token scheme referenced by authenticated routes.
+struct TokenSecurity;
+
+impl Modify for TokenSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+pub async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,248 @@
+use crate::{
+    http::{
+        AppState, Error, Result,
+        dashboard::{Bar, bars, render},
+        error::ErrorMessage,
+    },
+    queries::user::User,
+    stats,
+};
+use askama::Template;
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use time::{Date, Duration, OffsetDateTime, macros::format_description};
+use utoipa::ToSchema;
+
+time::serde::format_description!(iso_date, Date, "[year]-[month]-[day]");
+
+/// How far back weekly totals, streaks and project totals look.
+const HISTORY_DAYS: i64 = 365;
+const LANGUAGE_DAYS: i64 = 30;
+const TOP_LANGUAGES: usize = 10;
+const WEEKS: usize = 12;
+
+/// How long a profile is reused for. Changes to what the user made public
+/// apply right away, as they are part of the key.
+pub const PROFILE_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Public summary of a user's activity. Sections the user didn't opt in to
+/// are left out, and file paths are never included.
+#[derive(Clone, Serialize, ToSchema)]
+pub struct Profile {
+    username: String,
+    /// Top languages of the last 30 days
+    #[serde(skip_serializing_if = "Option::is_none")]
+    languages: Option<Vec<LanguageShare>>,
+    /// Coding time of the last 12 weeks, oldest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weekly_totals: Option<Vec<WeeklyTotal>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    streaks: Option<Streaks>,
+    /// Coding time of the last year in the projects made public
+    #[serde(skip_serializing_if = "Option::is_none")]
+    projects: Option<Vec<ProjectTotal>>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct LanguageShare {
+    language: String,
+    seconds: i64,
+    /// Share of the time spent in any language, from 0 to 100
+    percent: f64,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct WeeklyTotal {
+    /// Monday starting the week
+    #[serde(with = "iso_date")]
+    #[schema(value_type = String, format = Date)]
+    week: Date,
+    seconds: i64,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct Streaks {
+    /// Consecutive days with activity up to today
+    current: u32,
+    longest: u32,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct ProjectTotal {
+    project: String,
+    seconds: i64,
+}
+
+#[derive(Template)]
+#[template(path = "profile.html")]
+struct ProfileTemplate {
+    username: String,
+    languages: Option<Vec<Bar>>,
+    weekly_totals: Option<Vec<Bar>>,
+    streaks: Option<Streaks>,
+    projects: Option<Vec<Bar>>,
+}
+
+/// Public profile of a user, as HTML at `/u/octocat` or JSON at `/u/octocat.json`
+#[utoipa::path(
+    get,
+    path = "/u/{username}.json",
+    tag = "profiles",
+    params(("username" = String, Path, description = "GitHub username")),
+    responses(
+        (status = 200, description = "Public profile", body = Profile),
+        (status = 404, description = "No public profile for this user", body = ErrorMessage),
+        (status = 429, description = "Rate limit exceeded", body = ErrorMessage,
+            headers(("Retry-After" = u64, description = "Seconds until a request is accepted again"))),
+    )
+)]
+pub async fn show(State(state): State<AppState>, Path(file): Path<String>) -> Result<Response> {
+    let (username, json) = match file.strip_suffix(".json") {
+        Some(username) => (username, true),
+        None => (file.as_str(), false),
+    };
+
+    let user = crate::queries::user::find_by_username(&state.db, username)
+        .await?
+        .filter(|user| user.visibility.public_profile)
+        .ok_or_else(not_found)?;
+
+    let key = (user.id, user.visibility);
+    let profile = match state.profiles.get(&key) {
+        Some(profile) => profile,
+        None => {
+            let profile = build(&state, user).await?;
+            state.profiles.insert(key, profile.clone());
+            profile
+        }
+    };
+
+    if json {
+        return Ok(Json(profile).into_response());
+    }
+
+    let seconds = |(label, seconds): (String, i64)| (label, Duration::seconds(seconds));
+    let week_format = format_description!("[month repr:short] [day]");
+
+    Ok(render(ProfileTemplate {
+        username: profile.username,
+        languages: profile.languages.map(|languages| {
+            bars(
+                languages
+                    .into_iter()
+                    .map(|l| seconds((l.language, l.seconds)))
+                    .collect(),
+            )
+        }),
+        weekly_totals: profile.weekly_totals.map(|weeks| {
+            bars(
+                weeks
+                    .into_iter()
+                    .map(|w| {
+                        let label = w.week.format(week_format).unwrap_or_default();
+                        seconds((label, w.seconds))
+                    })
+                    .collect(),
+            )
+        }),
+        streaks: profile.streaks,
+        projects: profile.projects.map(|projects| {
+            bars(
+                projects
+                    .into_iter()
+                    .map(|p| seconds((p.project, p.seconds)))
+                    .collect(),
+            )
+        }),
+    })?
+    .into_response())
+}
+
+/// Same message whether the user doesn't exist or didn't opt in, so private
+/// accounts can't be discovered through profiles.
+fn not_found() -> Error {
+    Error::NotFound {
+        message: String::from("Profile not found"),
+    }
+}
+
+async fn build(state: &AppState, user: User) -> Result<Profile> {
+    let visibility = user.visibility;
+    let now = OffsetDateTime::now_utc();
+    let from = (now - Duration::days(HISTORY_DAYS - 1)).replace_time(time::Time::MIDNIGHT);
+
+    let heartbeats = crate::queries::events::list(&state.db, user.id, from, now).await?;
+    let activities = stats::activities(&heartbeats);
+    let daily = stats::by_day(&activities, from.date(), now.date());
+
+    let languages = visibility.profile_languages.then(|| {
+        let since = now - Duration::days(LANGUAGE_DAYS);
+        let recent: Vec<_> = activities
+            .iter()
+            .filter(|a| a.heartbeat.created_at >= since && a.heartbeat.language.is_some())
+            .copied()
+            .collect();
+        let total = stats::total(&recent).whole_seconds().max(1);
+
+        stats::group_by(&recent, |h| h.language.clone().unwrap_or_default())
+            .into_iter()
+            .take(TOP_LANGUAGES)
+            .map(|(language, duration)| LanguageShare {
+                language,
+                seconds: duration.whole_seconds(),
+                percent: (duration.whole_seconds() * 1000 / total) as f64 / 10.0,
+            })
+            .collect()
+    });
+
+    let weekly_totals = visibility.profile_weekly_totals.then(|| {
+        let weeks = stats::by_week(&daily);
+        weeks[weeks.len().saturating_sub(WEEKS)..]
+            .iter()
+            .map(|(week, duration)| WeeklyTotal {
+                week: *week,
+                seconds: duration.whole_seconds(),
+            })
+            .collect()
+    });
+
+    let streaks = visibility.profile_streaks.then(|| {
+        let streaks = stats::streaks(&daily);
+        Streaks {
+            current: streaks.current,
+            longest: streaks.longest,
+        }
+    });
+
+    let projects = if visibility.profile_projects {
+        let public = crate::queries::public_projects::list(&state.db, user.id).await?;
+
+        Some(
+            stats::group_by(&activities, |h| h.project.clone())
+                .into_iter()
+                .filter_map(|(project, duration)| {
+                    project
+                        .filter(|project| public.contains(project))
+                        .map(|project| ProjectTotal {
+                            project,
+                            seconds: duration.whole_seconds(),
+                        })
+                })
+                .collect(),
+        )
+    } else {
+        None
+    };
+
+    Ok(Profile {
+        username: user.username,
+        languages,
+        weekly_totals,
+        streaks,
+        projects,
+    })
+}
@@ -0,0 +1,175 @@
+use crate::http::{AppState, Error, Result, extractor::AuthUser};
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Number of buckets kept in memory, idle ones being pruned past it and new
+/// callers refused while none is idle.
+const MAX_BUCKETS: usize = 10_000;
+
+/// Least time between two prunes, as each one goes through every bucket.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct Buckets {
+    by_key: HashMap<String, Bucket>,
+    pruned_at: Instant,
+}
+
+/// Token bucket rate limiter keyed by user or client IP.
+///
+/// Each key gets a bucket holding up to `requests_per_minute` tokens that
+/// refills continuously, so short bursts are allowed while the sustained rate
+/// stays under the limit.
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<Buckets>>,
+    capacity: f64,
+    refill_per_sec: f64,
+    /// Reverse proxies whose `X-Forwarded-For` entries are believed
+    trusted_proxies: Arc<[IpAddr]>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_minute: u32, trusted_proxies: &[IpAddr]) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+
+        Self {
+            buckets: Arc::new(Mutex::new(Buckets {
+                by_key: HashMap::new(),
+                pruned_at: Instant::now(),
+            })),
+            capacity,
+            refill_per_sec: capacity / 60.0,
+            trusted_proxies: trusted_proxies.into(),
+        }
+    }
+
+    /// Address of the client: the peer of the request, or the one a trusted
+    /// proxy says it forwarded the request for, going through
+    /// `X-Forwarded-For` from the end while each hop is trusted. Over a Unix
+    /// socket only local processes connect, so the peer is the loopback
+    /// address.
+    fn client_ip(&self, parts: &Parts) -> IpAddr {
+        let mut ip = match parts.extensions.get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => addr.ip().to_canonical(),
+            None => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        };
+
+        let forwarded = parts
+            .headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        for hop in forwarded.into_iter().rev() {
+            if !self.trusted_proxies.contains(&ip) {
+                break;
+            }
+            match hop.trim().parse::<IpAddr>() {
+                Ok(hop) => ip = hop.to_canonical(),
+                Err(_) => break,
+            }
+        }
+
+        ip
+    }
+
+    /// Takes one token from the bucket of `key`, returning how long the
+    /// caller has to wait when the bucket is empty.
+    fn acquire(&self, key: &str) -> std::result::Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+
+        if !buckets.by_key.contains_key(key) && buckets.by_key.len() >= MAX_BUCKETS {
+            if now.duration_since(buckets.pruned_at) < PRUNE_INTERVAL {
+                return Err(PRUNE_INTERVAL);
+            }
+
+            let full_after = Duration::from_secs_f64(self.capacity / self.refill_per_sec);
+            buckets
+                .by_key
+                .retain(|_, bucket| now.duration_since(bucket.updated_at) < full_after);
+            buckets.pruned_at = now;
+            if buckets.by_key.len() >= MAX_BUCKETS {
+                return Err(PRUNE_INTERVAL);
+            }
+        }
+
+        let bucket = buckets.by_key.entry(key.to_owned()).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / self.refill_per_sec,
+        ))
+    }
+}
+
+/// Middleware rejecting requests with `429 Too Many Requests` once the
+/// bucket of the client IP is empty.
+pub async fn limit(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let (parts, body) = request.into_parts();
+    acquire(&limiter, &format!("ip:{}", limiter.client_ip(&parts)))?;
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+/// Same as [`limit`], keyed by the user once their token is validated, so
+/// making up tokens doesn't get a fresh bucket. Requests without a valid
+/// token fall back to the client IP, and are rejected by the handler.
+pub async fn limit_by_user(
+    State((limiter, state)): State<(RateLimiter, AppState)>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let (mut parts, body) = request.into_parts();
+    let key = match AuthUser::from_request_parts(&mut parts, &state).await {
+        Ok(user) => {
+            let key = format!("user:{}", *user.id);
+            // Spares the handler a second lookup of the token.
+            parts.extensions.insert(user.id);
+            key
+        }
+        Err(_) => format!("ip:{}", limiter.client_ip(&parts)),
+    };
+    acquire(&limiter, &key)?;
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+fn acquire(limiter: &RateLimiter, key: &str) -> Result<()> {
+    limiter
+        .acquire(key)
+        .map_err(|retry_after| Error::TooManyRequests {
+            retry_after: (retry_after.as_secs_f64().ceil() as u64).max(1),
+        })
+}
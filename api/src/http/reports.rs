@@ -0,0 +1,152 @@
+use crate::{
+    http::{AppState, Result, commits::MAX_LOOKBACK, error::ErrorMessage, extractor::AuthUser},
+    queries::commits::Commit,
+    stats,
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use time::{Date, Duration, OffsetDateTime};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 365;
+
+time::serde::format_description!(iso_date, Date, "[year]-[month]-[day]");
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TicketsQuery {
+    /// Only the time spent in this project
+    project: Option<String>,
+    /// Number of UTC days to include, ending today
+    #[param(minimum = 1, maximum = 365, default = 7)]
+    days: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TicketEntry {
+    #[schema(example = "PROJ-123")]
+    ticket: String,
+    seconds: i64,
+    /// Projects the ticket was worked on in, alphabetically
+    projects: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TicketsResponse {
+    #[serde(with = "iso_date")]
+    #[schema(value_type = String, format = Date)]
+    from: Date,
+    #[serde(with = "iso_date")]
+    #[schema(value_type = String, format = Date)]
+    to: Date,
+    /// Longest first
+    entries: Vec<TicketEntry>,
+}
+
+#[derive(Default)]
+struct Ticket {
+    duration: Duration,
+    projects: BTreeSet<String>,
+}
+
+/// Time per ticket of the authenticated user, from the ticket keys in the branch each event was
+/// on, or else in the message of the commit its time went into
+#[utoipa::path(
+    get,
+    path = "/reports/tickets",
+    tag = "stats",
+    params(TicketsQuery),
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Time per ticket", body = TicketsResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+    )
+)]
+pub async fn tickets(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<TicketsQuery>,
+) -> Result<Json<TicketsResponse>> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let now = OffsetDateTime::now_utc();
+    let from = (now - Duration::days(days - 1)).replace_time(time::Time::MIDNIGHT);
+    let patterns = &state.config.ticket_patterns;
+
+    let heartbeats = crate::queries::events::list(&state.db, auth_user.id, from, now).await?;
+    let activities = stats::activities(&heartbeats);
+    let commits =
+        crate::queries::commits::list(&state.db, auth_user.id, query.project.as_deref(), from, now)
+            .await?;
+
+    let mut commits_by_project: HashMap<&str, Vec<&Commit>> = HashMap::new();
+    for commit in &commits {
+        commits_by_project
+            .entry(&commit.project)
+            .or_default()
+            .push(commit);
+    }
+
+    let mut tickets: HashMap<String, Ticket> = HashMap::new();
+    for activity in &activities {
+        let heartbeat = activity.heartbeat;
+        let Some(project) = heartbeat.project.as_deref() else {
+            continue;
+        };
+        if !activity.duration.is_positive()
+            || query.project.as_deref().is_some_and(|p| p != project)
+        {
+            continue;
+        }
+
+        let mut keys = heartbeat
+            .branch
+            .as_deref()
+            .map(|branch| stats::tickets(patterns, branch))
+            .unwrap_or_default();
+        if keys.is_empty() {
+            // The commit or checkout ending this stretch of work, as in `/commits`.
+            let next = commits_by_project.get(project).and_then(|commits| {
+                let i = commits.partition_point(|c| c.created_at < heartbeat.created_at);
+                commits.get(i)
+            });
+            if let Some(commit) = next
+                && commit.kind == "commit"
+                && commit.created_at - heartbeat.created_at <= MAX_LOOKBACK
+                && let Some(message) = &commit.message
+            {
+                keys = stats::tickets(patterns, message);
+            }
+        }
+
+        for key in keys {
+            let ticket = tickets.entry(key).or_default();
+            ticket.duration += activity.duration;
+            ticket.projects.insert(project.to_owned());
+        }
+    }
+
+    let mut entries: Vec<TicketEntry> = tickets
+        .into_iter()
+        .map(|(ticket, t)| TicketEntry {
+            ticket,
+            seconds: t.duration.whole_seconds(),
+            projects: t.projects.into_iter().collect(),
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.seconds
+            .cmp(&a.seconds)
+            .then_with(|| a.ticket.cmp(&b.ticket))
+    });
+
+    Ok(Json(TicketsResponse {
+        from: from.date(),
+        to: now.date(),
+        entries,
+    }))
+}
@@ -0,0 +1,148 @@
+use crate::{
+    http::{AppState, Result, error::ErrorMessage, extractor::AuthUser},
+    queries::events::Heartbeat,
+    stats,
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use time::{Date, Duration, OffsetDateTime};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 365;
+
+time::serde::format_description!(iso_date, Date, "[year]-[month]-[day]");
+
+#[derive(Clone, Copy, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Project,
+    Language,
+    Editor,
+    /// Machine ID, labelled with its latest hostname
+    Machine,
+    /// Kind of work: coding, debugging, docs, tests or config
+    Category,
+    /// Where the time was spent: editor or terminal
+    Kind,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+    group_by: GroupBy,
+    /// Number of UTC days to include, ending today
+    #[param(minimum = 1, maximum = 365, default = 7)]
+    days: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StatsEntry {
+    /// Value grouped on, `null` for events sent without it
+    key: Option<String>,
+    /// Latest hostname, when grouping by machine
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
+    seconds: i64,
+    lines_added: i64,
+    lines_removed: i64,
+    typed_chars: i64,
+    pasted_chars: i64,
+    bulk_chars: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StatsResponse {
+    group_by: GroupBy,
+    #[serde(with = "iso_date")]
+    #[schema(value_type = String, format = Date)]
+    from: Date,
+    #[serde(with = "iso_date")]
+    #[schema(value_type = String, format = Date)]
+    to: Date,
+    total_seconds: i64,
+    lines_added: i64,
+    lines_removed: i64,
+    /// Non-whitespace characters typed
+    typed_chars: i64,
+    /// Non-whitespace characters pasted
+    pasted_chars: i64,
+    /// Non-whitespace characters inserted in bulk, e.g. by an AI assistant
+    bulk_chars: i64,
+    /// Longest first
+    entries: Vec<StatsEntry>,
+}
+
+/// Coding time of the authenticated user, grouped by project, language, editor, machine, category or kind
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "stats",
+    params(StatsQuery),
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Coding time per group", body = StatsResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+    )
+)]
+pub async fn show(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<StatsResponse>> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let now = OffsetDateTime::now_utc();
+    let from = (now - Duration::days(days - 1)).replace_time(time::Time::MIDNIGHT);
+
+    let heartbeats = crate::queries::events::list(&state.db, auth_user.id, from, now).await?;
+    let activities = stats::activities(&heartbeats);
+
+    let key: fn(&Heartbeat) -> Option<String> = match query.group_by {
+        GroupBy::Project => |h| h.project.clone(),
+        GroupBy::Language => |h| h.language.clone(),
+        GroupBy::Editor => |h| h.editor.clone(),
+        GroupBy::Machine => |h| h.machine_id.clone(),
+        GroupBy::Category => |h| h.category.clone(),
+        GroupBy::Kind => |h| Some(h.kind.clone()),
+    };
+    let churn = stats::churn_by(&activities, key);
+    let total_churn = stats::churn(&activities);
+
+    let hostnames = match query.group_by {
+        GroupBy::Machine => stats::hostnames(&heartbeats),
+        _ => HashMap::new(),
+    };
+
+    Ok(Json(StatsResponse {
+        group_by: query.group_by,
+        from: from.date(),
+        to: now.date(),
+        total_seconds: stats::total(&activities).whole_seconds(),
+        lines_added: total_churn.added,
+        lines_removed: total_churn.removed,
+        typed_chars: total_churn.typed_chars,
+        pasted_chars: total_churn.pasted_chars,
+        bulk_chars: total_churn.bulk_chars,
+        entries: stats::group_by(&activities, key)
+            .into_iter()
+            .map(|(key, duration)| {
+                let churn = churn.get(&key).copied().unwrap_or_default();
+
+                StatsEntry {
+                    hostname: key.as_ref().and_then(|k| hostnames.get(k).cloned()),
+                    key,
+                    seconds: duration.whole_seconds(),
+                    lines_added: churn.added,
+                    lines_removed: churn.removed,
+                    typed_chars: churn.typed_chars,
+                    pasted_chars: churn.pasted_chars,
+                    bulk_chars: churn.bulk_chars,
+                }
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,75 @@
+use anyhow::Context;
+use axum::Router;
+use axum_server::{Handle, tls_rustls::RustlsConfig};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Serves `app` over HTTPS, picking up renewed certificates without a restart.
+pub async fn serve(
+    addr: SocketAddr,
+    cert: &Path,
+    key: &Path,
+    reload_interval: Duration,
+    app: Router,
+) -> anyhow::Result<()> {
+    // Fails only when a provider is already installed, which is fine.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let tls_config = RustlsConfig::from_pem_file(cert, key)
+        .await
+        .context("Error when trying to load TLS certificate")?;
+
+    tokio::spawn(reload_on_change(
+        tls_config.clone(),
+        cert.to_owned(),
+        key.to_owned(),
+        reload_interval,
+    ));
+
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            super::shutdown_signal().await;
+            handle.graceful_shutdown(Some(Duration::from_secs(30)));
+        }
+    });
+
+    axum_server::bind_rustls(addr, tls_config)
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .context("Error when trying to run HTTPS server")
+}
+
+async fn reload_on_change(config: RustlsConfig, cert: PathBuf, key: PathBuf, every: Duration) {
+    let mut last_modified = modified_at(&cert, &key);
+    let mut interval = tokio::time::interval(every);
+
+    loop {
+        interval.tick().await;
+
+        let modified = modified_at(&cert, &key);
+        if modified == last_modified {
+            continue;
+        }
+
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => {
+                tracing::info!("reloaded TLS certificate");
+                last_modified = modified;
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to reload TLS certificate"),
+        }
+    }
+}
+
+fn modified_at(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let cert = std::fs::metadata(cert).and_then(|m| m.modified()).ok()?;
+    let key = std::fs::metadata(key).and_then(|m| m.modified()).ok()?;
+
+    Some((cert, key))
+}
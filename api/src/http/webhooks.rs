@@ -0,0 +1,310 @@
+use crate::{
+    http::{
+        AppState, Error, Result,
+        auth::generate_token,
+        error::{ErrorMessage, ValidationErrors},
+        extractor::{AuthUser, JsonBody},
+    },
+    webhooks::Event,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use time::OffsetDateTime;
+use utoipa::ToSchema;
+
+const MAX_URL_LENGTH: usize = 2048;
+const MIN_SECRET_LENGTH: usize = 16;
+const DELIVERY_LOG_LENGTH: i64 = 50;
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    /// `http` or `https` URL receiving the payloads, on a public address
+    #[schema(max_length = 2048)]
+    url: String,
+    /// Events to receive, at least one
+    events: Vec<Event>,
+    /// Key of the `X-Cairos-Signature` HMAC, generated when missing
+    #[schema(min_length = 16)]
+    secret: Option<String>,
+    /// Receive the events of every user, admins only
+    #[serde(default)]
+    global: bool,
+}
+
+impl CreateWebhookRequest {
+    fn validate(mut self) -> Result<Self> {
+        let mut errors: Vec<(&'static str, Cow<'static, str>)> = Vec::new();
+
+        self.url = self.url.trim().to_owned();
+        if self.url.len() > MAX_URL_LENGTH {
+            errors.push((
+                "url",
+                format!("must be at most {MAX_URL_LENGTH} bytes").into(),
+            ));
+        }
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            Ok(_) => errors.push(("url", "must be an http or https URL".into())),
+            Err(_) => errors.push(("url", "must be a valid URL".into())),
+        }
+
+        if self.events.is_empty() {
+            errors.push(("events", "must not be empty".into()));
+        }
+        let mut events = Vec::with_capacity(self.events.len());
+        for event in self.events {
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+        self.events = events;
+
+        if self
+            .secret
+            .as_ref()
+            .is_some_and(|secret| secret.len() < MIN_SECRET_LENGTH)
+        {
+            errors.push((
+                "secret",
+                format!("must be at least {MIN_SECRET_LENGTH} bytes").into(),
+            ));
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::unprocessable_entity(errors));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Webhook {
+    id: i32,
+    url: String,
+    events: Vec<String>,
+    global: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    webhook: Webhook,
+    /// Only returned once, verify payloads with it
+    secret: String,
+}
+
+/// Registers a URL receiving signed JSON payloads
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    security(("token" = [])),
+    responses(
+        (status = 201, description = "Webhook registered", body = CreatedWebhook),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+        (status = 403, description = "Only admins can register global webhooks", body = ErrorMessage),
+        (status = 422, description = "Invalid webhook", body = ValidationErrors),
+    )
+)]
+pub async fn create(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>)> {
+    let payload = payload.validate()?;
+    let url = reqwest::Url::parse(&payload.url).expect("validated URL");
+    if let Err(e) = crate::webhooks::check_destination(&url).await {
+        return Err(Error::unprocessable_entity([("url", e)]));
+    }
+
+    if payload.global {
+        let username = crate::queries::user::find_username(&state.db, auth_user.id).await?;
+        if !state.config.admins.contains(&username) {
+            return Err(Error::Forbidden);
+        }
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let secret = payload.secret.unwrap_or_else(generate_token);
+    let events: Vec<String> = payload
+        .events
+        .iter()
+        .map(|event| event.as_str().to_owned())
+        .collect();
+
+    let id = crate::queries::webhooks::create(
+        &state.db,
+        &crate::queries::webhooks::CreateParams {
+            user_id: auth_user.id,
+            url: payload.url.clone(),
+            secret: secret.clone(),
+            events: events.clone(),
+            global: payload.global,
+            now,
+        },
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedWebhook {
+            webhook: Webhook {
+                id,
+                url: payload.url,
+                events,
+                global: payload.global,
+                created_at: now,
+            },
+            secret,
+        }),
+    ))
+}
+
+/// Lists the webhooks of the authenticated user
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "webhooks",
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Registered webhooks", body = Vec<Webhook>),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+    )
+)]
+pub async fn list(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Webhook>>> {
+    let webhooks = crate::queries::webhooks::list(&state.db, auth_user.id).await?;
+
+    Ok(Json(
+        webhooks
+            .into_iter()
+            .map(|w| Webhook {
+                id: w.id,
+                url: w.url,
+                events: w.events,
+                global: w.global,
+                created_at: w.created_at,
+            })
+            .collect(),
+    ))
+}
+
+/// Removes a webhook along with its pending deliveries
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Webhook removed"),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+        (status = 404, description = "Webhook not found", body = ErrorMessage),
+    )
+)]
+pub async fn delete(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode> {
+    if !crate::queries::webhooks::delete(&state.db, auth_user.id, id).await? {
+        return Err(not_found());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Every attempt failed
+    Failed,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Delivery {
+    id: i64,
+    event: String,
+    status: DeliveryStatus,
+    /// JSON body sent
+    payload: Value,
+    attempts: i32,
+    /// HTTP status of the last attempt
+    last_status: Option<i32>,
+    last_error: Option<String>,
+    /// When a pending delivery is tried next
+    #[serde(with = "time::serde::rfc3339")]
+    next_attempt_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    delivered_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+}
+
+/// Latest deliveries of a webhook, newest first
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Last 50 deliveries", body = Vec<Delivery>),
+        (status = 401, description = "Missing or invalid token", body = ErrorMessage),
+        (status = 404, description = "Webhook not found", body = ErrorMessage),
+    )
+)]
+pub async fn deliveries(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<Delivery>>> {
+    if !crate::queries::webhooks::exists(&state.db, auth_user.id, id).await? {
+        return Err(not_found());
+    }
+
+    let deliveries =
+        crate::queries::webhooks::list_deliveries(&state.db, id, DELIVERY_LOG_LENGTH).await?;
+
+    Ok(Json(
+        deliveries
+            .into_iter()
+            .map(|d| Delivery {
+                id: d.id,
+                event: d.event,
+                status: match (d.delivered_at, d.failed_at) {
+                    (Some(_), _) => DeliveryStatus::Delivered,
+                    (None, Some(_)) => DeliveryStatus::Failed,
+                    (None, None) => DeliveryStatus::Pending,
+                },
+                payload: d.payload,
+                attempts: d.attempts,
+                last_status: d.last_status,
+                last_error: d.last_error,
+                next_attempt_at: d.next_attempt_at,
+                delivered_at: d.delivered_at,
+                created_at: d.created_at,
+            })
+            .collect(),
+    ))
+}
+
+fn not_found() -> Error {
+    Error::NotFound {
+        message: String::from("Webhook not found"),
+    }
+}
@@ -0,0 +1,25 @@
+use tracing_subscriber::EnvFilter;
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum LogFormat {
+    /// One JSON object per line, for log aggregators
+    Json,
+    /// Human readable lines, for local development
+    Text,
+}
+
+/// Installs the global subscriber, filtered by `RUST_LOG` (defaults to `info`).
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    match format {
+        LogFormat::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .init(),
+        LogFormat::Text => builder.init(),
+    }
+}
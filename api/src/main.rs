@@ -4,15 +4,19 @@ use sqlx::postgres::PgPoolOptions;
 
 mod config;
 mod http;
+mod logging;
 mod queries;
+mod stats;
+mod webhooks;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::from_filename(".env").ok();
     dotenvy::from_filename("api/.env").ok();
-    env_logger::init();
 
     let config = config::Config::parse();
+    logging::init(config.log_format);
+
     let db = PgPoolOptions::new()
         .max_connections(config.database_max_connections)
         .connect(&config.database_url)
@@ -18,5 +18,26 @@ pub async fn create(
     )
     .execute(db)
     .await
-    .and_then(|_| Ok(()))
+    .map(|_| ())
+}
+
+/// Disables a token, returning its owner when it was still active.
+pub async fn disable(
+    db: &sqlx::PgPool,
+    token: &str,
+    now: OffsetDateTime,
+) -> QueryResult<Option<UserId>> {
+    sqlx::query_scalar!(
+        r#"
+            UPDATE auth_tokens
+            SET disabled_at = $2
+            WHERE token = $1 AND disabled_at IS NULL
+            RETURNING user_id
+        "#,
+        token,
+        now,
+    )
+    .fetch_optional(db)
+    .await
+    .map(|o| o.map(UserId))
 }
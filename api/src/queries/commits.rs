@@ -0,0 +1,86 @@
+use crate::queries::{QueryResult, user::UserId};
+use time::OffsetDateTime;
+
+pub struct CreateParams {
+    pub user_id: UserId,
+    pub project: String,
+    pub sha: String,
+    pub kind: String,
+    pub branch: Option<String>,
+    pub message: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    pub files: Vec<String>,
+    pub authored_at: Option<OffsetDateTime>,
+    pub now: OffsetDateTime,
+}
+
+/// Records a commit or checkout, a commit sent twice being kept once.
+pub async fn create(db: &sqlx::PgPool, p: &CreateParams) -> QueryResult<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO commits (
+                user_id, project, sha, kind, branch, message, author_name, author_email, files,
+                authored_at, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (user_id, project, sha) WHERE kind = 'commit' DO NOTHING
+        "#,
+        *p.user_id,
+        p.project,
+        p.sha,
+        p.kind,
+        p.branch,
+        p.message,
+        p.author_name,
+        p.author_email,
+        &p.files,
+        p.authored_at,
+        p.now,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+pub struct Commit {
+    pub project: String,
+    pub sha: String,
+    pub kind: String,
+    pub branch: Option<String>,
+    pub message: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    pub files: Vec<String>,
+    pub authored_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+/// Commits and checkouts of a user in `[from, to)`, optionally of a single
+/// project, oldest first.
+pub async fn list(
+    db: &sqlx::PgPool,
+    user_id: UserId,
+    project: Option<&str>,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> QueryResult<Vec<Commit>> {
+    sqlx::query_as!(
+        Commit,
+        r#"
+            SELECT
+                project, sha, kind, branch, message, author_name, author_email, files,
+                authored_at, created_at
+            FROM commits
+            WHERE user_id = $1 AND ($2::TEXT IS NULL OR project = $2)
+                AND created_at >= $3 AND created_at < $4
+            ORDER BY created_at
+        "#,
+        *user_id,
+        project,
+        from,
+        to,
+    )
+    .fetch_all(db)
+    .await
+}
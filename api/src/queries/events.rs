@@ -1,5 +1,5 @@
 use crate::queries::{QueryResult, user::UserId};
-use time::OffsetDateTime;
+use time::{Date, OffsetDateTime};
 
 pub struct CreateParams {
     pub uri: String,
@@ -7,6 +7,24 @@ pub struct CreateParams {
     pub language: Option<String>,
     pub line_number: Option<i32>,
     pub cursor_pos: Option<i32>,
+    pub project: Option<String>,
+    pub editor: Option<String>,
+    pub editor_version: Option<String>,
+    pub client_version: Option<String>,
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    pub machine_id: Option<String>,
+    pub hostname: Option<String>,
+    pub lines_added: i32,
+    pub lines_removed: i32,
+    pub typed_chars: i32,
+    pub pasted_chars: i32,
+    pub bulk_chars: i32,
+    pub category: Option<String>,
+    pub kind: String,
+    pub command: Option<String>,
+    pub duration_seconds: Option<i32>,
+    pub branch: Option<String>,
     pub user_id: UserId,
     pub now: OffsetDateTime,
 }
@@ -14,18 +32,140 @@ pub struct CreateParams {
 pub async fn create(db: &sqlx::PgPool, p: &CreateParams) -> QueryResult<()> {
     sqlx::query!(
         r#"
-            INSERT INTO events (uri, is_write, language, line_number, cursor_pos, user_id, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO events (
+                uri, is_write, language, line_number, cursor_pos, project, editor, editor_version,
+                client_version, os, arch, machine_id, hostname, lines_added, lines_removed,
+                typed_chars, pasted_chars, bulk_chars, category, kind, command, duration_seconds,
+                branch, user_id, created_at
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                $19, $20, $21, $22, $23, $24, $25
+            )
         "#,
         p.uri,
         p.is_write,
         p.language,
         p.line_number,
         p.cursor_pos,
+        p.project,
+        p.editor,
+        p.editor_version,
+        p.client_version,
+        p.os,
+        p.arch,
+        p.machine_id,
+        p.hostname,
+        p.lines_added,
+        p.lines_removed,
+        p.typed_chars,
+        p.pasted_chars,
+        p.bulk_chars,
+        p.category,
+        p.kind,
+        p.command,
+        p.duration_seconds,
+        p.branch,
         *p.user_id,
         p.now,
     )
     .execute(db)
     .await
-    .and_then(|_| Ok(()))
+    .map(|_| ())
+}
+
+/// Moves the events of `from`, or of the files below it when it is a folder,
+/// to `to`. Returns how many were moved.
+pub async fn rename(db: &sqlx::PgPool, user_id: UserId, from: &str, to: &str) -> QueryResult<u64> {
+    sqlx::query!(
+        r#"
+            UPDATE events
+            SET uri = $3 || substr(uri, length($2) + 1)
+            WHERE user_id = $1 AND (uri = $2 OR starts_with(uri, $2 || '/'))
+        "#,
+        *user_id,
+        from,
+        to,
+    )
+    .execute(db)
+    .await
+    .map(|result| result.rows_affected())
+}
+
+/// Projects the user has activity in, alphabetically.
+pub async fn list_projects(db: &sqlx::PgPool, user_id: UserId) -> QueryResult<Vec<String>> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT DISTINCT project AS "project!"
+            FROM events
+            WHERE user_id = $1 AND project IS NOT NULL
+            ORDER BY project
+        "#,
+        *user_id,
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub struct Heartbeat {
+    pub language: Option<String>,
+    pub project: Option<String>,
+    pub editor: Option<String>,
+    pub machine_id: Option<String>,
+    pub hostname: Option<String>,
+    pub lines_added: i32,
+    pub lines_removed: i32,
+    pub typed_chars: i32,
+    pub pasted_chars: i32,
+    pub bulk_chars: i32,
+    pub category: Option<String>,
+    pub kind: String,
+    /// How long a terminal command ran, in seconds
+    pub duration_seconds: Option<i32>,
+    pub branch: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+/// Events of a user in `[from, to)`, oldest first.
+pub async fn list(
+    db: &sqlx::PgPool,
+    user_id: UserId,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> QueryResult<Vec<Heartbeat>> {
+    sqlx::query_as!(
+        Heartbeat,
+        r#"
+            SELECT
+                language, project, editor, machine_id, hostname, lines_added, lines_removed,
+                typed_chars, pasted_chars, bulk_chars, category, kind, duration_seconds, branch,
+                created_at
+            FROM events
+            WHERE user_id = $1 AND created_at >= $2 AND created_at < $3
+            ORDER BY created_at
+        "#,
+        *user_id,
+        from,
+        to,
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Records that the user was active on `day`, returning whether it is the
+/// first time.
+pub async fn claim_day(db: &sqlx::PgPool, user_id: UserId, day: Date) -> QueryResult<bool> {
+    sqlx::query_scalar!(
+        r#"
+            INSERT INTO active_days (user_id, day)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            RETURNING user_id
+        "#,
+        *user_id,
+        day,
+    )
+    .fetch_optional(db)
+    .await
+    .map(|claimed| claimed.is_some())
 }
@@ -1,5 +1,16 @@
 pub mod auth_tokens;
+pub mod commits;
 pub mod events;
+pub mod public_projects;
+pub mod sessions;
 pub mod user;
+pub mod webhooks;
 
 pub(super) type QueryResult<T> = Result<T, sqlx::Error>;
+
+pub async fn ping(db: &sqlx::PgPool) -> QueryResult<()> {
+    sqlx::query!("SELECT 1 AS one")
+        .fetch_one(db)
+        .await
+        .map(|_| ())
+}
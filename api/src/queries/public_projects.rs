@@ -0,0 +1,60 @@
+use time::OffsetDateTime;
+
+use crate::queries::{QueryResult, user::UserId};
+
+pub async fn list(db: &sqlx::PgPool, user_id: UserId) -> QueryResult<Vec<String>> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT project FROM public_projects WHERE user_id = $1 ORDER BY project
+        "#,
+        *user_id,
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn exists(db: &sqlx::PgPool, user_id: UserId, project: &str) -> QueryResult<bool> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS (SELECT 1 FROM public_projects WHERE user_id = $1 AND project = $2) AS "exists!"
+        "#,
+        *user_id,
+        project,
+    )
+    .fetch_one(db)
+    .await
+}
+
+/// Makes exactly `projects` public for the user.
+pub async fn replace(
+    db: &sqlx::PgPool,
+    user_id: UserId,
+    projects: &[String],
+    now: OffsetDateTime,
+) -> QueryResult<()> {
+    let mut tx = db.begin().await?;
+
+    sqlx::query!(
+        r#"
+            DELETE FROM public_projects WHERE user_id = $1
+        "#,
+        *user_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO public_projects (user_id, project, created_at)
+            SELECT $1, project, $3 FROM UNNEST($2::TEXT[]) AS project
+            ON CONFLICT DO NOTHING
+        "#,
+        *user_id,
+        projects,
+        now,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
@@ -0,0 +1,49 @@
+use time::OffsetDateTime;
+
+use crate::queries::{QueryResult, user::UserId};
+
+pub async fn create(
+    db: &sqlx::PgPool,
+    user_id: UserId,
+    token: &str,
+    expires_at: OffsetDateTime,
+    now: OffsetDateTime,
+) -> QueryResult<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO sessions (user_id, token, expires_at, created_at) VALUES ($1, $2, $3, $4)
+        "#,
+        *user_id,
+        token,
+        expires_at,
+        now,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+pub async fn delete(db: &sqlx::PgPool, token: &str) -> QueryResult<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM sessions WHERE token = $1
+        "#,
+        token,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+/// Deletes the sessions that expired by `now`, as they can't be used anymore.
+pub async fn delete_expired(db: &sqlx::PgPool, now: OffsetDateTime) -> QueryResult<()> {
+    sqlx::query!(
+        r#"
+            DELETE FROM sessions WHERE expires_at <= $1
+        "#,
+        now,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
@@ -3,8 +3,8 @@ use std::ops::Deref;
 use super::QueryResult;
 use time::OffsetDateTime;
 
-#[derive(Debug)]
-pub struct UserId(i32);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserId(pub(super) i32);
 
 impl Deref for UserId {
     type Target = i32;
@@ -14,6 +14,51 @@ impl Deref for UserId {
     }
 }
 
+pub struct User {
+    pub id: UserId,
+    pub username: String,
+    pub visibility: Visibility,
+}
+
+/// What the user chose to make public.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Visibility {
+    pub public_badges: bool,
+    pub public_profile: bool,
+    pub profile_languages: bool,
+    pub profile_weekly_totals: bool,
+    pub profile_streaks: bool,
+    pub profile_projects: bool,
+}
+
+struct UserRow {
+    id: i32,
+    username: String,
+    public_badges: bool,
+    public_profile: bool,
+    profile_languages: bool,
+    profile_weekly_totals: bool,
+    profile_streaks: bool,
+    profile_projects: bool,
+}
+
+impl From<UserRow> for User {
+    fn from(r: UserRow) -> Self {
+        Self {
+            id: UserId(r.id),
+            username: r.username,
+            visibility: Visibility {
+                public_badges: r.public_badges,
+                public_profile: r.public_profile,
+                profile_languages: r.profile_languages,
+                profile_weekly_totals: r.profile_weekly_totals,
+                profile_streaks: r.profile_streaks,
+                profile_projects: r.profile_projects,
+            },
+        }
+    }
+}
+
 pub struct CreateParams {
     pub username: String,
     pub email: String,
@@ -37,7 +82,7 @@ pub async fn create(db: &sqlx::PgPool, p: &CreateParams) -> QueryResult<UserId>
     )
     .fetch_one(db)
     .await
-    .and_then(|i| Ok(UserId(i)))
+    .map(UserId)
 }
 
 pub async fn find_user_id_from_token(
@@ -55,5 +100,83 @@ pub async fn find_user_id_from_token(
     )
     .fetch_optional(db)
     .await
-    .and_then(|o| Ok(o.map(|i| UserId(i))))
+    .map(|o| o.map(UserId))
+}
+
+pub async fn find_username(db: &sqlx::PgPool, user_id: UserId) -> QueryResult<String> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT username FROM users WHERE id = $1
+        "#,
+        *user_id,
+    )
+    .fetch_one(db)
+    .await
+}
+
+pub async fn find_user_from_session(
+    db: &sqlx::PgPool,
+    token: &str,
+    now: OffsetDateTime,
+) -> QueryResult<Option<User>> {
+    sqlx::query_as!(
+        UserRow,
+        r#"
+            SELECT
+                users.id, users.username, users.public_badges, users.public_profile,
+                users.profile_languages, users.profile_weekly_totals, users.profile_streaks,
+                users.profile_projects
+            FROM sessions
+            INNER JOIN users ON users.id = sessions.user_id
+            WHERE sessions.token = $1 AND sessions.expires_at > $2;
+        "#,
+        token,
+        now,
+    )
+    .fetch_optional(db)
+    .await
+    .map(|o| o.map(User::from))
+}
+
+pub async fn find_by_username(db: &sqlx::PgPool, username: &str) -> QueryResult<Option<User>> {
+    sqlx::query_as!(
+        UserRow,
+        r#"
+            SELECT
+                id, username, public_badges, public_profile, profile_languages,
+                profile_weekly_totals, profile_streaks, profile_projects
+            FROM users
+            WHERE username = $1
+        "#,
+        username,
+    )
+    .fetch_optional(db)
+    .await
+    .map(|o| o.map(User::from))
+}
+
+pub async fn set_visibility(db: &sqlx::PgPool, user_id: UserId, v: &Visibility) -> QueryResult<()> {
+    sqlx::query!(
+        r#"
+            UPDATE users
+            SET
+                public_badges = $2,
+                public_profile = $3,
+                profile_languages = $4,
+                profile_weekly_totals = $5,
+                profile_streaks = $6,
+                profile_projects = $7
+            WHERE id = $1
+        "#,
+        *user_id,
+        v.public_badges,
+        v.public_profile,
+        v.profile_languages,
+        v.profile_weekly_totals,
+        v.profile_streaks,
+        v.profile_projects,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
 }
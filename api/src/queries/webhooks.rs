@@ -0,0 +1,281 @@
+use serde_json::Value;
+use time::{Date, OffsetDateTime};
+
+use crate::queries::{QueryResult, user::UserId};
+
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<String>,
+    pub global: bool,
+    pub created_at: OffsetDateTime,
+}
+
+pub struct CreateParams {
+    pub user_id: UserId,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub global: bool,
+    pub now: OffsetDateTime,
+}
+
+pub async fn create(db: &sqlx::PgPool, p: &CreateParams) -> QueryResult<i32> {
+    sqlx::query_scalar!(
+        r#"
+            INSERT INTO webhooks (user_id, url, secret, events, global, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+        "#,
+        *p.user_id,
+        p.url,
+        p.secret,
+        &p.events,
+        p.global,
+        p.now,
+    )
+    .fetch_one(db)
+    .await
+}
+
+pub async fn list(db: &sqlx::PgPool, user_id: UserId) -> QueryResult<Vec<Webhook>> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+            SELECT id, url, events, global, created_at
+            FROM webhooks
+            WHERE user_id = $1
+            ORDER BY id
+        "#,
+        *user_id,
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn exists(db: &sqlx::PgPool, user_id: UserId, id: i32) -> QueryResult<bool> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT EXISTS (SELECT 1 FROM webhooks WHERE user_id = $1 AND id = $2) AS "exists!"
+        "#,
+        *user_id,
+        id,
+    )
+    .fetch_one(db)
+    .await
+}
+
+/// Deletes a webhook along with its deliveries, returning whether it existed.
+pub async fn delete(db: &sqlx::PgPool, user_id: UserId, id: i32) -> QueryResult<bool> {
+    sqlx::query!(
+        r#"
+            DELETE FROM webhooks WHERE user_id = $1 AND id = $2
+        "#,
+        *user_id,
+        id,
+    )
+    .execute(db)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+/// Queues `event` for every webhook subscribed to it, either owned by the
+/// user or global. Returns the number of deliveries queued.
+pub async fn enqueue(
+    db: &sqlx::PgPool,
+    user_id: UserId,
+    event: &str,
+    data: &Value,
+    now: OffsetDateTime,
+) -> QueryResult<u64> {
+    sqlx::query!(
+        r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload, next_attempt_at, created_at)
+            SELECT
+                webhooks.id,
+                $2,
+                jsonb_build_object(
+                    'event', $2::TEXT,
+                    'user', users.username,
+                    'created_at', $4::TIMESTAMPTZ,
+                    'data', $3::JSONB
+                ),
+                $4,
+                $4
+            FROM webhooks, users
+            WHERE users.id = $1
+                AND (webhooks.user_id = $1 OR webhooks.global)
+                AND $2 = ANY(webhooks.events)
+        "#,
+        *user_id,
+        event,
+        data,
+        now,
+    )
+    .execute(db)
+    .await
+    .map(|result| result.rows_affected())
+}
+
+pub struct PendingDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event: String,
+    pub payload: Value,
+    /// Including the one about to be made
+    pub attempts: i32,
+}
+
+/// Takes up to `limit` due deliveries and hides them from other workers
+/// until `lease_until`, so a crashed worker's deliveries are retried.
+pub async fn claim(
+    db: &sqlx::PgPool,
+    now: OffsetDateTime,
+    lease_until: OffsetDateTime,
+    limit: i64,
+) -> QueryResult<Vec<PendingDelivery>> {
+    sqlx::query_as!(
+        PendingDelivery,
+        r#"
+            UPDATE webhook_deliveries
+            SET next_attempt_at = $2, attempts = webhook_deliveries.attempts + 1
+            FROM webhooks
+            WHERE webhooks.id = webhook_deliveries.webhook_id
+                AND webhook_deliveries.id IN (
+                    SELECT id
+                    FROM webhook_deliveries
+                    WHERE delivered_at IS NULL AND failed_at IS NULL AND next_attempt_at <= $1
+                    ORDER BY next_attempt_at
+                    LIMIT $3
+                    FOR UPDATE SKIP LOCKED
+                )
+            RETURNING
+                webhook_deliveries.id, webhooks.url, webhooks.secret, webhook_deliveries.event,
+                webhook_deliveries.payload, webhook_deliveries.attempts
+        "#,
+        now,
+        lease_until,
+        limit,
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn mark_delivered(
+    db: &sqlx::PgPool,
+    id: i64,
+    status: i32,
+    now: OffsetDateTime,
+) -> QueryResult<()> {
+    sqlx::query!(
+        r#"
+            UPDATE webhook_deliveries
+            SET delivered_at = $3, last_status = $2, last_error = NULL
+            WHERE id = $1
+        "#,
+        id,
+        status,
+        now,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+pub struct FailureParams<'a> {
+    pub status: Option<i32>,
+    pub error: &'a str,
+    /// When to try again, or `None` to give up
+    pub retry_at: Option<OffsetDateTime>,
+    pub now: OffsetDateTime,
+}
+
+pub async fn mark_failed(db: &sqlx::PgPool, id: i64, p: &FailureParams<'_>) -> QueryResult<()> {
+    sqlx::query!(
+        r#"
+            UPDATE webhook_deliveries
+            SET
+                last_status = $2,
+                last_error = $3,
+                next_attempt_at = COALESCE($4, next_attempt_at),
+                failed_at = CASE WHEN $4::TIMESTAMPTZ IS NULL THEN $5::TIMESTAMPTZ END
+            WHERE id = $1
+        "#,
+        id,
+        p.status,
+        p.error,
+        p.retry_at,
+        p.now,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+pub struct Delivery {
+    pub id: i64,
+    pub event: String,
+    pub payload: Value,
+    pub attempts: i32,
+    pub last_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: OffsetDateTime,
+    pub delivered_at: Option<OffsetDateTime>,
+    pub failed_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+pub async fn list_deliveries(
+    db: &sqlx::PgPool,
+    webhook_id: i32,
+    limit: i64,
+) -> QueryResult<Vec<Delivery>> {
+    sqlx::query_as!(
+        Delivery,
+        r#"
+            SELECT
+                id, event, payload, attempts, last_status, last_error, next_attempt_at,
+                delivered_at, failed_at, created_at
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+        "#,
+        webhook_id,
+        limit,
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Records that `day` gets summarized for the users active in `[from, to)`
+/// with a webhook listening for summaries, returning the ones not done yet.
+pub async fn claim_summaries(
+    db: &sqlx::PgPool,
+    day: Date,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> QueryResult<Vec<UserId>> {
+    sqlx::query_scalar!(
+        r#"
+            INSERT INTO webhook_summaries (user_id, day)
+            SELECT DISTINCT events.user_id, $1::DATE
+            FROM events
+            WHERE events.created_at >= $2 AND events.created_at < $3
+                AND EXISTS (
+                    SELECT 1 FROM webhooks
+                    WHERE (webhooks.user_id = events.user_id OR webhooks.global)
+                        AND 'daily_summary' = ANY(webhooks.events)
+                )
+            ON CONFLICT DO NOTHING
+            RETURNING user_id
+        "#,
+        day,
+        from,
+        to,
+    )
+    .fetch_all(db)
+    .await
+    .map(|ids| ids.into_iter().map(UserId).collect())
+}
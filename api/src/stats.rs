@@ -0,0 +1,255 @@
+use crate::queries::events::Heartbeat;
+use std::{collections::HashMap, hash::Hash};
+use time::{Date, Duration, OffsetDateTime};
+
+/// Gaps between two events longer than this are counted as a break.
+pub const IDLE_TIMEOUT: Duration = Duration::minutes(15);
+
+/// A heartbeat with the coding time credited to it.
+#[derive(Clone, Copy)]
+pub struct Activity<'a> {
+    pub heartbeat: &'a Heartbeat,
+    pub duration: Duration,
+}
+
+/// Credits each heartbeat with the time until the next one, unless the user
+/// was idle in between. A terminal command followed by a break is still
+/// credited with how long it ran, up to `IDLE_TIMEOUT` like any other event,
+/// so a build isn't taken for a break. The last heartbeat gets nothing, as
+/// nothing tells how long it went on. `heartbeats` must be sorted by
+/// `created_at`.
+pub fn activities(heartbeats: &[Heartbeat]) -> Vec<Activity<'_>> {
+    heartbeats
+        .iter()
+        .enumerate()
+        .map(|(i, heartbeat)| {
+            let gap = heartbeats
+                .get(i + 1)
+                .map(|next| next.created_at - heartbeat.created_at);
+            let ran = Duration::seconds(heartbeat.duration_seconds.unwrap_or(0).into());
+
+            let duration = match gap {
+                Some(gap) if gap <= IDLE_TIMEOUT => gap,
+                Some(_) => ran.min(IDLE_TIMEOUT),
+                None => Duration::ZERO,
+            };
+
+            Activity {
+                heartbeat,
+                duration,
+            }
+        })
+        .collect()
+}
+
+pub fn total(activities: &[Activity]) -> Duration {
+    activities.iter().map(|a| a.duration).sum()
+}
+
+/// Time spent per key, longest first. Keys without any time are dropped.
+pub fn group_by<K, F>(activities: &[Activity], key: F) -> Vec<(K, Duration)>
+where
+    K: Eq + Hash + Ord,
+    F: Fn(&Heartbeat) -> K,
+{
+    let mut groups: HashMap<K, Duration> = HashMap::new();
+
+    for activity in activities {
+        *groups.entry(key(activity.heartbeat)).or_default() += activity.duration;
+    }
+
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .filter(|(_, duration)| duration.is_positive())
+        .collect();
+    groups.sort_by(|(a_key, a), (b_key, b)| b.cmp(a).then_with(|| a_key.cmp(b_key)));
+    groups
+}
+
+/// Time spent on `project` in `(from, to]`, e.g. the work that went into a
+/// commit made at `to`.
+pub fn project_between(
+    activities: &[Activity],
+    project: &str,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> Duration {
+    activities
+        .iter()
+        .filter(|a| a.heartbeat.project.as_deref() == Some(project))
+        .filter(|a| a.heartbeat.created_at > from && a.heartbeat.created_at <= to)
+        .map(|a| a.duration.min(to - a.heartbeat.created_at))
+        .sum()
+}
+
+/// Ticket keys matched by `patterns` in a branch name or commit message,
+/// without duplicates.
+pub fn tickets(patterns: &[regex::Regex], text: &str) -> Vec<String> {
+    let mut tickets: Vec<String> = Vec::new();
+
+    for pattern in patterns {
+        for found in pattern.find_iter(text) {
+            if !tickets.iter().any(|t| t == found.as_str()) {
+                tickets.push(found.as_str().to_owned());
+            }
+        }
+    }
+
+    tickets
+}
+
+/// Lines added and removed, a rough measure of code churn, along with the
+/// characters inserted by typing, pasting or in bulk.
+#[derive(Clone, Copy, Default)]
+pub struct Churn {
+    pub added: i64,
+    pub removed: i64,
+    pub typed_chars: i64,
+    pub pasted_chars: i64,
+    pub bulk_chars: i64,
+}
+
+impl Churn {
+    /// Share of the inserted characters that were typed, from 0 to 100.
+    pub fn typed_percent(&self) -> i64 {
+        let inserted = self.typed_chars + self.pasted_chars + self.bulk_chars;
+        self.typed_chars * 100 / inserted.max(1)
+    }
+}
+
+pub fn churn(activities: &[Activity]) -> Churn {
+    churn_by(activities, |_| ()).remove(&()).unwrap_or_default()
+}
+
+/// Lines changed per key, whether or not time was credited to the heartbeats.
+pub fn churn_by<K, F>(activities: &[Activity], key: F) -> HashMap<K, Churn>
+where
+    K: Eq + Hash,
+    F: Fn(&Heartbeat) -> K,
+{
+    let mut groups: HashMap<K, Churn> = HashMap::new();
+
+    for activity in activities {
+        let churn = groups.entry(key(activity.heartbeat)).or_default();
+        let heartbeat = activity.heartbeat;
+        churn.added += i64::from(heartbeat.lines_added);
+        churn.removed += i64::from(heartbeat.lines_removed);
+        churn.typed_chars += i64::from(heartbeat.typed_chars);
+        churn.pasted_chars += i64::from(heartbeat.pasted_chars);
+        churn.bulk_chars += i64::from(heartbeat.bulk_chars);
+    }
+
+    groups
+}
+
+/// Latest hostname seen for each machine ID. `heartbeats` must be sorted by `created_at`.
+pub fn hostnames(heartbeats: &[Heartbeat]) -> HashMap<String, String> {
+    heartbeats
+        .iter()
+        .filter_map(|h| Some((h.machine_id.clone()?, h.hostname.clone()?)))
+        .collect()
+}
+
+/// Time spent on each UTC day in `[from, to]`, including days without activity.
+pub fn by_day(activities: &[Activity], from: Date, to: Date) -> Vec<(Date, Duration)> {
+    let days = group_by(activities, |h| h.created_at.date())
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let mut totals = Vec::new();
+    let mut day = from;
+    while day <= to {
+        totals.push((day, days.get(&day).copied().unwrap_or_default()));
+        let Some(next) = day.next_day() else { break };
+        day = next;
+    }
+
+    totals
+}
+
+/// Sums daily totals into weeks, keyed by the Monday starting each week.
+pub fn by_week(daily: &[(Date, Duration)]) -> Vec<(Date, Duration)> {
+    let mut weeks: Vec<(Date, Duration)> = Vec::new();
+
+    for (day, duration) in daily {
+        let monday = *day - Duration::days(day.weekday().number_days_from_monday().into());
+
+        match weeks.last_mut() {
+            Some((week, total)) if *week == monday => *total += *duration,
+            _ => weeks.push((monday, *duration)),
+        }
+    }
+
+    weeks
+}
+
+pub struct Streaks {
+    /// Consecutive active days up to today, or yesterday while today is still empty
+    pub current: u32,
+    pub longest: u32,
+}
+
+/// Counts runs of days with activity in daily totals ending today.
+pub fn streaks(daily: &[(Date, Duration)]) -> Streaks {
+    let mut longest = 0;
+    let mut run = 0;
+
+    for (_, duration) in daily {
+        run = if duration.is_positive() { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+
+    let today_empty = daily.last().is_some_and(|(_, d)| !d.is_positive());
+    let current = daily
+        .iter()
+        .rev()
+        .skip(usize::from(today_empty))
+        .take_while(|(_, d)| d.is_positive())
+        .count() as u32;
+
+    Streaks { current, longest }
+}
+
+/// A stretch of uninterrupted activity.
+pub struct Session {
+    pub start: OffsetDateTime,
+    pub end: OffsetDateTime,
+    /// Project the most time was spent on
+    pub project: Option<String>,
+}
+
+/// Splits activities into sessions wherever the user was idle, newest first.
+pub fn sessions(activities: &[Activity]) -> Vec<Session> {
+    let mut sessions = Vec::new();
+
+    for chunk in activities.chunk_by(|a, _| a.duration.is_positive()) {
+        let (Some(first), Some(last)) = (chunk.first(), chunk.last()) else {
+            continue;
+        };
+        if first.heartbeat.created_at == last.heartbeat.created_at {
+            continue;
+        }
+
+        sessions.push(Session {
+            start: first.heartbeat.created_at,
+            end: last.heartbeat.created_at,
+            project: group_by(chunk, |h| h.project.clone())
+                .into_iter()
+                .next()
+                .and_then(|(project, _)| project),
+        });
+    }
+
+    sessions.reverse();
+    sessions
+}
+
+/// Formats a duration as `3h 05m`, or `12m` when under an hour.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.whole_minutes();
+
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {:02}m", hours, minutes % 60),
+    }
+}
@@ -0,0 +1,309 @@
+use crate::{queries::user::UserId, stats};
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration as StdDuration,
+};
+use time::{Date, Duration, OffsetDateTime, Time};
+use utoipa::ToSchema;
+
+const SIGNATURE_HEADER: &str = "x-cairos-signature";
+const EVENT_HEADER: &str = "x-cairos-event";
+const DELIVERY_HEADER: &str = "x-cairos-delivery";
+
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(5);
+const REQUEST_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+const BATCH_SIZE: i64 = 20;
+
+/// How long a claimed delivery stays hidden from other workers.
+const LEASE: Duration = Duration::minutes(2);
+
+/// Attempts before a delivery is given up, spread over about four hours.
+const MAX_ATTEMPTS: i32 = 10;
+const FIRST_RETRY: Duration = Duration::seconds(30);
+
+const SUMMARY_TOP: usize = 5;
+
+/// Something webhooks can subscribe to.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// Totals of the previous UTC day, sent shortly after midnight
+    DailySummary,
+    /// First activity of a UTC day
+    FirstEventOfDay,
+    /// An API token was issued
+    TokenCreated,
+    /// An API token was revoked
+    TokenRevoked,
+}
+
+impl Event {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DailySummary => "daily_summary",
+            Self::FirstEventOfDay => "first_event_of_day",
+            Self::TokenCreated => "token_created",
+            Self::TokenRevoked => "token_revoked",
+        }
+    }
+}
+
+/// Queues `event` for the webhooks of `user_id` and the global ones. Errors
+/// are only logged, webhooks never fail the request that triggered them.
+pub async fn enqueue(db: &PgPool, user_id: UserId, event: Event, data: Value) {
+    let result = crate::queries::webhooks::enqueue(
+        db,
+        user_id,
+        event.as_str(),
+        &data,
+        OffsetDateTime::now_utc(),
+    )
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(error = %e, event = event.as_str(), "failed to queue webhook");
+    }
+}
+
+/// `sha256=<hex>` HMAC of the request body, sent in `X-Cairos-Signature`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Whether webhooks may be sent to `ip`, which rules out the API's own host
+/// and the networks around it.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // Shared address space of carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    || ip.is_multicast())
+            }
+        },
+    }
+}
+
+/// Addresses of `host`, refused when any of them isn't public.
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("failed to resolve {host}: {e}"))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(format!("{host} has no address"));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        return Err(format!(
+            "{host} resolves to {}, which is not public",
+            addr.ip()
+        ));
+    }
+
+    Ok(addrs)
+}
+
+/// Checks that the host of `url` only resolves to public addresses, when a
+/// webhook is created and again before each delivery.
+pub async fn check_destination(url: &reqwest::Url) -> Result<(), String> {
+    let port = url.port_or_known_default().unwrap_or(80);
+    match url.host() {
+        Some(url::Host::Domain(domain)) => resolve_public(domain, port).await.map(|_| ()),
+        Some(url::Host::Ipv4(ip)) if is_public(ip.into()) => Ok(()),
+        Some(url::Host::Ipv6(ip)) if is_public(ip.into()) => Ok(()),
+        Some(host) => Err(format!("{host} is not a public address")),
+        None => Err("URL has no host".to_owned()),
+    }
+}
+
+/// Resolver of the delivery client, so a host can't switch to a private
+/// address between the check before a delivery and its connection.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addrs = resolve_public(name.as_str(), 0).await?;
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+
+            Ok(addrs)
+        })
+    }
+}
+
+/// Client sending the deliveries, which doesn't follow redirects as they
+/// could point anywhere.
+fn delivery_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent("CAIROS/1.0.0")
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("Error on build Client.")
+}
+
+/// Background worker sending queued deliveries and daily summaries. Several
+/// API instances can run it against the same database.
+pub async fn run(db: PgPool) {
+    let client = delivery_client();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut summarized: Option<Date> = None;
+
+    loop {
+        interval.tick().await;
+
+        let today = OffsetDateTime::now_utc().date();
+        if summarized != Some(today) {
+            match queue_summaries(&db, today).await {
+                Ok(()) => summarized = Some(today),
+                Err(e) => tracing::error!(error = format!("{e:#}"), "failed to queue summaries"),
+            }
+        }
+
+        if let Err(e) = deliver_due(&db, &client).await {
+            tracing::error!(error = format!("{e:#}"), "failed to deliver webhooks");
+        }
+    }
+}
+
+/// Queues the summary of the day before `today` for every active user.
+async fn queue_summaries(db: &PgPool, today: Date) -> anyhow::Result<()> {
+    let Some(day) = today.previous_day() else {
+        return Ok(());
+    };
+    let from = day.with_time(Time::MIDNIGHT).assume_utc();
+    let to = today.with_time(Time::MIDNIGHT).assume_utc();
+
+    let users = crate::queries::webhooks::claim_summaries(db, day, from, to)
+        .await
+        .context("Error when trying to claim summaries")?;
+
+    for user_id in users {
+        let heartbeats = crate::queries::events::list(db, user_id, from, to)
+            .await
+            .context("Error when trying to list events")?;
+        let activities = stats::activities(&heartbeats);
+
+        let top = |groups: Vec<(Option<String>, Duration)>| -> Vec<Value> {
+            groups
+                .into_iter()
+                .filter_map(|(name, duration)| Some((name?, duration)))
+                .take(SUMMARY_TOP)
+                .map(
+                    |(name, duration)| json!({ "name": name, "seconds": duration.whole_seconds() }),
+                )
+                .collect()
+        };
+
+        let data = json!({
+            "day": day.to_string(),
+            "total_seconds": stats::total(&activities).whole_seconds(),
+            "projects": top(stats::group_by(&activities, |h| h.project.clone())),
+            "languages": top(stats::group_by(&activities, |h| h.language.clone())),
+        });
+
+        enqueue(db, user_id, Event::DailySummary, data).await;
+    }
+
+    Ok(())
+}
+
+async fn deliver_due(db: &PgPool, client: &reqwest::Client) -> anyhow::Result<()> {
+    let now = OffsetDateTime::now_utc();
+    let deliveries = crate::queries::webhooks::claim(db, now, now + LEASE, BATCH_SIZE)
+        .await
+        .context("Error when trying to claim deliveries")?;
+
+    for delivery in deliveries {
+        let body = serde_json::to_vec(&delivery.payload)?;
+
+        let result = match reqwest::Url::parse(&delivery.url) {
+            Ok(url) => match check_destination(&url).await {
+                Ok(()) => client
+                    .post(url)
+                    .timeout(REQUEST_TIMEOUT)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header(SIGNATURE_HEADER, sign(&delivery.secret, &body))
+                    .header(EVENT_HEADER, &delivery.event)
+                    .header(DELIVERY_HEADER, delivery.id.to_string())
+                    .body(body)
+                    .send()
+                    .await
+                    .map_err(|e| format!("{e:#}")),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(format!("Invalid URL: {e}")),
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let (status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                crate::queries::webhooks::mark_delivered(
+                    db,
+                    delivery.id,
+                    response.status().as_u16().into(),
+                    now,
+                )
+                .await?;
+                metrics::counter!("webhook_deliveries_total", "outcome" => "success").increment(1);
+                continue;
+            }
+            Ok(response) => (
+                Some(response.status().as_u16().into()),
+                format!("Endpoint responded with {}", response.status()),
+            ),
+            Err(e) => (None, e),
+        };
+
+        let retry_at = (delivery.attempts < MAX_ATTEMPTS)
+            .then(|| now + FIRST_RETRY * 2_i32.pow(delivery.attempts as u32 - 1));
+
+        tracing::warn!(
+            delivery_id = delivery.id,
+            attempts = delivery.attempts,
+            error,
+            "webhook delivery failed"
+        );
+        metrics::counter!("webhook_deliveries_total", "outcome" => "failure").increment(1);
+
+        crate::queries::webhooks::mark_failed(
+            db,
+            delivery.id,
+            &crate::queries::webhooks::FailureParams {
+                status,
+                error: &error,
+                retry_at,
+                now,
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use anyhow::Context;
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Kind of work an event is part of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Coding,
+    Debugging,
+    Docs,
+    Tests,
+    Config,
+}
+
+impl Category {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Coding => "coding",
+            Self::Debugging => "debugging",
+            Self::Docs => "docs",
+            Self::Tests => "tests",
+            Self::Config => "config",
+        }
+    }
+}
+
+/// `[[categories]]` entry of the config file, overriding the inferred category.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct CategoryRule {
+    /// Glob matched against the full path, e.g. `**/scripts/**`
+    pub pattern: String,
+    pub category: Category,
+}
+
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec", "specs", "testdata"];
+const DOC_DIRS: &[&str] = &["doc", "docs", "documentation"];
+const DOC_EXTENSIONS: &[&str] = &["md", "mdx", "markdown", "rst", "adoc", "org"];
+const DOC_FILES: &[&str] = &["readme", "changelog", "license", "contributing", "authors"];
+const CONFIG_EXTENSIONS: &[&str] = &[
+    "toml",
+    "yaml",
+    "yml",
+    "ini",
+    "cfg",
+    "conf",
+    "properties",
+    "env",
+    "lock",
+];
+const CONFIG_FILES: &[&str] = &[
+    "package-lock.json",
+    "pnpm-lock.yaml",
+    "go.sum",
+    "dockerfile",
+    "makefile",
+    ".env",
+    ".gitignore",
+    ".gitattributes",
+    ".editorconfig",
+    ".gitlab-ci.yml",
+];
+const CI_DIRS: &[&str] = &[".github", ".circleci", ".buildkite"];
+const DEBUG_FILES: &[&str] = &["launch.json", ".gdbinit", ".lldbinit"];
+
+/// Picks the category of a file from the config rules, then from path
+/// conventions, falling back to coding.
+pub struct Categorizer {
+    rules: Vec<(GlobMatcher, Category)>,
+}
+
+impl Categorizer {
+    pub fn new(rules: &[CategoryRule]) -> anyhow::Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let glob = GlobBuilder::new(&rule.pattern)
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("invalid category pattern `{}`", rule.pattern))?;
+                Ok((glob.compile_matcher(), rule.category))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Rules are matched against the full `path`, conventions against the
+    /// part of it within `root`, so the folders holding the project, like
+    /// `~/tests/`, don't count.
+    pub fn categorize(&self, path: &str, root: Option<&str>) -> Category {
+        let within_root = root
+            .and_then(|root| path.strip_prefix(root.trim_end_matches('/')))
+            .filter(|rest| rest.starts_with('/'))
+            .unwrap_or(path);
+
+        self.rules
+            .iter()
+            .find(|(glob, _)| glob.is_match(path))
+            .map(|(_, category)| *category)
+            .unwrap_or_else(|| infer(within_root))
+    }
+}
+
+/// Category from path conventions alone.
+pub fn infer(path: &str) -> Category {
+    let path = Path::new(path);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let stem = file_name.split('.').next().unwrap_or_default().to_owned();
+    let dirs: Vec<String> = path
+        .parent()
+        .into_iter()
+        .flat_map(|parent| parent.iter())
+        .map(|dir| dir.to_string_lossy().to_lowercase())
+        .collect();
+    let in_dir = |names: &[&str]| dirs.iter().any(|dir| names.contains(&dir.as_str()));
+
+    let is_test = in_dir(TEST_DIRS)
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.");
+    if is_test {
+        return Category::Tests;
+    }
+
+    if DEBUG_FILES.contains(&file_name.as_str()) || extension == "log" {
+        return Category::Debugging;
+    }
+
+    if DOC_EXTENSIONS.contains(&extension.as_str())
+        || DOC_FILES.contains(&stem.as_str())
+        || in_dir(DOC_DIRS)
+    {
+        return Category::Docs;
+    }
+
+    if CONFIG_EXTENSIONS.contains(&extension.as_str())
+        || CONFIG_FILES.contains(&file_name.as_str())
+        || in_dir(CI_DIRS)
+    {
+        return Category::Config;
+    }
+
+    Category::Coding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer_from_conventions() {
+        assert_eq!(infer("/p/src/main.rs"), Category::Coding);
+        assert_eq!(infer("/p/tests/api.rs"), Category::Tests);
+        assert_eq!(infer("/p/src/parser_test.go"), Category::Tests);
+        assert_eq!(infer("/p/src/app.spec.ts"), Category::Tests);
+        assert_eq!(infer("/p/test_utils.py"), Category::Tests);
+        assert_eq!(infer("/p/.vscode/launch.json"), Category::Debugging);
+        assert_eq!(infer("/p/server.log"), Category::Debugging);
+        assert_eq!(infer("/p/README.md"), Category::Docs);
+        assert_eq!(infer("/p/LICENSE"), Category::Docs);
+        assert_eq!(infer("/p/docs/guide.html"), Category::Docs);
+        assert_eq!(infer("/p/Cargo.toml"), Category::Config);
+        assert_eq!(infer("/p/Dockerfile"), Category::Config);
+        assert_eq!(infer("/p/.github/workflows/ci.json"), Category::Config);
+    }
+
+    #[test]
+    fn infer_unknown_files_as_coding() {
+        assert_eq!(infer("/p/notes.txt"), Category::Coding);
+        assert_eq!(infer("/p/data.xyz"), Category::Coding);
+        assert_eq!(infer("/p/Makefile.bak"), Category::Coding);
+        assert_eq!(infer(""), Category::Coding);
+    }
+
+    #[test]
+    fn infer_handles_non_ascii_names() {
+        assert_eq!(infer("/p/tests/ünïcode.rs"), Category::Tests);
+        assert_eq!(infer("/p/文档/说明.MD"), Category::Docs);
+    }
+
+    #[test]
+    fn categorize_ignores_folders_above_the_root() {
+        let categorizer = Categorizer::new(&[]).unwrap();
+
+        assert_eq!(
+            categorizer.categorize("/home/me/tests/app/src/lib.rs", Some("/home/me/tests/app")),
+            Category::Coding
+        );
+        assert_eq!(
+            categorizer.categorize("/home/me/tests/app/src/lib.rs", Some("/home/me/tests/app/")),
+            Category::Coding
+        );
+        assert_eq!(
+            categorizer.categorize("/home/me/tests/app/src/lib.rs", None),
+            Category::Tests
+        );
+        // A sibling sharing the root as a prefix isn't within it.
+        assert_eq!(
+            categorizer.categorize(
+                "/home/me/tests/application/lib.rs",
+                Some("/home/me/tests/app")
+            ),
+            Category::Tests
+        );
+    }
+
+    #[test]
+    fn categorize_prefers_rules() {
+        let categorizer = Categorizer::new(&[CategoryRule {
+            pattern: "**/scripts/*.sh".to_owned(),
+            category: Category::Debugging,
+        }])
+        .unwrap();
+
+        assert_eq!(
+            categorizer.categorize("/p/scripts/run.sh", Some("/p")),
+            Category::Debugging
+        );
+        // Single stars don't cross folders.
+        assert_eq!(
+            categorizer.categorize("/p/scripts/ci/run.sh", Some("/p")),
+            Category::Coding
+        );
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let rules = [CategoryRule {
+            pattern: "[".to_owned(),
+            category: Category::Docs,
+        }];
+
+        assert!(Categorizer::new(&rules).is_err());
+    }
+}
@@ -1,5 +1,6 @@
-use anyhow::Context;
+use crate::commands::shell_hook::Shell;
 use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(version, about)]
@@ -17,6 +18,20 @@ pub enum Commands {
         base_url: String,
     },
     LanguageServer,
+    /// Reports activity on a file, for editors without a language server
+    Heartbeat(HeartbeatArgs),
+    /// Prints a hook recording terminal commands, e.g. for `eval "$(cairos shell-hook zsh)"`
+    #[command(arg_required_else_help = true)]
+    ShellHook {
+        shell: Shell,
+    },
+    /// Reports a finished terminal command, called by the shell hook
+    #[command(hide = true)]
+    ShellEvent(ShellEventArgs),
+    /// Installs or runs the git hooks recording commits and branch switches
+    GitHook(GitHookArgs),
+    /// Prints reports of the time spent, like the time per ticket
+    Report(ReportArgs),
 }
 
 #[derive(Args)]
@@ -36,6 +51,88 @@ pub enum AuthCommands {
     Logout,
 }
 
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct GitHookArgs {
+    #[command(subcommand)]
+    pub command: GitHookCommands,
+}
+
+#[derive(Subcommand)]
+pub enum GitHookCommands {
+    /// Adds hooks sending commits and branch switches to a repository
+    Install {
+        /// Repository, the current directory by default
+        #[arg(long, default_value = ".")]
+        repo: PathBuf,
+    },
+    #[command(hide = true)]
+    PostCommit,
+    /// Arguments are those git passes to the hook
+    #[command(hide = true)]
+    PostCheckout {
+        previous: String,
+        new: String,
+        flag: String,
+    },
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct ReportArgs {
+    #[command(subcommand)]
+    pub command: ReportCommands,
+}
+
+#[derive(Subcommand)]
+pub enum ReportCommands {
+    /// Time per ticket, from the ticket keys in branch names and commit messages
+    Tickets {
+        /// Number of days to include, ending today
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+        /// Only the time spent in this project
+        #[arg(long)]
+        project: Option<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct HeartbeatArgs {
+    /// File being worked on
+    #[arg(long)]
+    pub file: PathBuf,
+    /// Detected from the file name or shebang when omitted
+    #[arg(long)]
+    pub language: Option<String>,
+    /// The file was saved
+    #[arg(long)]
+    pub write: bool,
+    /// Line of the cursor, starting at 1
+    #[arg(long)]
+    pub line: Option<u32>,
+    /// Defaults to the folder name of the enclosing git repository
+    #[arg(long)]
+    pub project: Option<String>,
+    /// Name of the editor or script reporting, e.g. `vim`
+    #[arg(long)]
+    pub editor: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ShellEventArgs {
+    #[arg(long)]
+    pub shell: Shell,
+    /// Command line, of which only the program is sent by default
+    #[arg(long)]
+    pub command: String,
+    #[arg(long)]
+    pub cwd: PathBuf,
+    /// Unix time the command started at
+    #[arg(long)]
+    pub started_at: i64,
+}
+
 impl Cli {
     pub async fn run(self, ctx: &crate::Ctx) -> anyhow::Result<()> {
         match self.command {
@@ -46,19 +143,62 @@ impl Cli {
                             .await?
                     }
                 }
-                AuthCommands::Logout => {}
+                AuthCommands::Logout => {
+                    crate::commands::auth::logout(
+                        &ctx.reqwest,
+                        &ctx.config.base_url,
+                        ctx.config.token.as_deref(),
+                    )
+                    .await?
+                }
             },
             Commands::Setup { base_url } => crate::commands::config::setup(base_url)?,
             Commands::LanguageServer => {
-                crate::commands::language_server::run(
-                    ctx.reqwest.clone(),
-                    &ctx.config.base_url,
-                    &ctx.config
-                        .token
-                        .as_ref()
-                        .context("you are not authenticated")?,
-                )
-                .await
+                crate::commands::language_server::run(ctx.reqwest.clone(), &ctx.config).await?
+            }
+            Commands::Heartbeat(args) => {
+                let heartbeat = crate::commands::heartbeat::Heartbeat {
+                    file: args.file,
+                    language: args.language,
+                    is_write: args.write,
+                    line: args.line,
+                    project: args.project,
+                    editor: args.editor,
+                };
+                crate::commands::heartbeat::send(&ctx.reqwest, &ctx.config, heartbeat).await?
+            }
+            Commands::GitHook(git_hook) => match git_hook.command {
+                GitHookCommands::Install { repo } => {
+                    crate::commands::git_hook::install(&repo).await?
+                }
+                GitHookCommands::PostCommit => {
+                    crate::commands::git_hook::post_commit(&ctx.reqwest, &ctx.config).await?
+                }
+                GitHookCommands::PostCheckout { flag, .. } => {
+                    crate::commands::git_hook::post_checkout(&ctx.reqwest, &ctx.config, &flag)
+                        .await?
+                }
+            },
+            Commands::Report(report) => match report.command {
+                ReportCommands::Tickets { days, project } => {
+                    crate::commands::report::tickets(
+                        &ctx.reqwest,
+                        &ctx.config,
+                        project.as_deref(),
+                        days,
+                    )
+                    .await?
+                }
+            },
+            Commands::ShellHook { shell } => crate::commands::shell_hook::print(shell)?,
+            Commands::ShellEvent(args) => {
+                let run = crate::commands::shell_hook::CommandRun {
+                    shell: args.shell,
+                    command: args.command,
+                    cwd: args.cwd,
+                    started_at: args.started_at,
+                };
+                crate::commands::shell_hook::record(&ctx.reqwest, &ctx.config, run).await?
             }
         }
 
@@ -8,6 +8,20 @@ pub enum Error {
     Deserialization,
 }
 
+impl Error {
+    /// Whether the same request may succeed later, i.e. the server couldn't
+    /// be reached, failed or throttled it.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Request(None, _) => true,
+            Self::Request(Some(status), _) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Self::Deserialization => false,
+        }
+    }
+}
+
 pub mod cairos {
     use super::Error;
     use reqwest::{
@@ -47,26 +61,195 @@ pub mod cairos {
         }
     }
 
-    #[derive(Serialize)]
+    pub async fn logout(
+        reqwest: &reqwest::Client,
+        base_url: &str,
+        api_token: &str,
+    ) -> Result<(), Error> {
+        let result = reqwest
+            .post(format!("{base_url}/auth/logout"))
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {api_token}"))
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                if !status.is_success() {
+                    let text = response.text().await.unwrap_or(String::new());
+                    return Err(Error::Request(Some(status), text));
+                }
+
+                Ok(())
+            }
+            Err(error) => Err(Error::Request(error.status(), error.to_string())),
+        }
+    }
+
+    #[derive(Deserialize, Serialize)]
     pub struct SendEventsParams {
         pub uri: String,
+        #[serde(with = "time::serde::rfc3339")]
+        pub timestamp: time::OffsetDateTime,
         pub is_write: bool,
         pub language: Option<String>,
         pub line_number: Option<i32>,
         pub cursor_pos: Option<i32>,
+        pub project: Option<String>,
+        pub editor: Option<String>,
+        pub editor_version: Option<String>,
+        pub client_version: String,
+        pub os: String,
+        pub arch: String,
+        pub machine_id: Option<String>,
+        pub hostname: Option<String>,
+        pub lines_added: i32,
+        pub lines_removed: i32,
+        pub typed_chars: i32,
+        pub pasted_chars: i32,
+        pub bulk_chars: i32,
+        pub category: String,
+        #[serde(default)]
+        pub kind: EventKind,
+        pub command: Option<String>,
+        pub duration_seconds: Option<i32>,
+        pub branch: Option<String>,
+    }
+
+    #[derive(Clone, Copy, Default, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum EventKind {
+        #[default]
+        Editor,
+        /// A shell command, run in the directory sent as `uri`
+        Terminal,
+    }
+
+    /// Events for the same file closer than this are sent as one.
+    const COALESCE_WINDOW: time::Duration = time::Duration::seconds(2);
+
+    impl SendEventsParams {
+        /// Folds `next` into this event when both are for the same file at
+        /// about the same time, returning whether it did. Terminal commands
+        /// are each kept.
+        pub fn merge(&mut self, next: &Self) -> bool {
+            if self.kind != EventKind::Editor
+                || next.kind != EventKind::Editor
+                || next.uri != self.uri
+                || next.is_write != self.is_write
+                || next.timestamp - self.timestamp >= COALESCE_WINDOW
+            {
+                return false;
+            }
+
+            self.line_number = next.line_number;
+            self.cursor_pos = next.cursor_pos;
+            self.lines_added += next.lines_added;
+            self.lines_removed += next.lines_removed;
+            self.typed_chars += next.typed_chars;
+            self.pasted_chars += next.pasted_chars;
+            self.bulk_chars += next.bulk_chars;
+            true
+        }
     }
 
     pub async fn send_events(
         reqwest: &reqwest::Client,
         base_url: &str,
         api_token: &str,
-        p: SendEventsParams,
+        p: &SendEventsParams,
     ) -> Result<(), Error> {
         let result = reqwest
             .post(format!("{base_url}/events/capture"))
             .header(ACCEPT, "application/json")
             .header(AUTHORIZATION, format!("Bearer {api_token}"))
-            .json(&p)
+            .json(p)
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                if status != StatusCode::OK {
+                    let text = response.text().await.unwrap_or(String::new());
+                    return Err(Error::Request(Some(status), text));
+                }
+
+                Ok(())
+            }
+            Err(error) => Err(Error::Request(error.status(), error.to_string())),
+        }
+    }
+
+    #[derive(Serialize)]
+    pub struct RenameEventsParams {
+        pub from: String,
+        pub to: String,
+    }
+
+    pub async fn rename_events(
+        reqwest: &reqwest::Client,
+        base_url: &str,
+        api_token: &str,
+        p: &RenameEventsParams,
+    ) -> Result<(), Error> {
+        let result = reqwest
+            .post(format!("{base_url}/events/rename"))
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {api_token}"))
+            .json(p)
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                if status != StatusCode::OK {
+                    let text = response.text().await.unwrap_or(String::new());
+                    return Err(Error::Request(Some(status), text));
+                }
+
+                Ok(())
+            }
+            Err(error) => Err(Error::Request(error.status(), error.to_string())),
+        }
+    }
+
+    #[derive(Clone, Copy, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum CommitKind {
+        Commit,
+        Checkout,
+    }
+
+    #[derive(Serialize)]
+    pub struct SendCommitParams {
+        pub project: String,
+        pub sha: String,
+        pub kind: CommitKind,
+        pub branch: Option<String>,
+        pub message: Option<String>,
+        pub author_name: Option<String>,
+        pub author_email: Option<String>,
+        pub files: Vec<String>,
+        #[serde(with = "time::serde::rfc3339::option")]
+        pub authored_at: Option<time::OffsetDateTime>,
+        #[serde(with = "time::serde::rfc3339")]
+        pub timestamp: time::OffsetDateTime,
+    }
+
+    pub async fn send_commit(
+        reqwest: &reqwest::Client,
+        base_url: &str,
+        api_token: &str,
+        p: &SendCommitParams,
+    ) -> Result<(), Error> {
+        let result = reqwest
+            .post(format!("{base_url}/commits"))
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {api_token}"))
+            .json(p)
             .send()
             .await;
 
@@ -83,6 +266,99 @@ pub mod cairos {
             Err(error) => Err(Error::Request(error.status(), error.to_string())),
         }
     }
+
+    #[derive(Deserialize)]
+    pub struct StatsEntry {
+        pub key: Option<String>,
+        pub seconds: i64,
+    }
+
+    #[derive(Deserialize)]
+    pub struct StatsResponse {
+        pub total_seconds: i64,
+        pub entries: Vec<StatsEntry>,
+    }
+
+    pub async fn get_stats(
+        reqwest: &reqwest::Client,
+        base_url: &str,
+        api_token: &str,
+        group_by: &str,
+        days: i64,
+    ) -> Result<StatsResponse, Error> {
+        let result = reqwest
+            .get(format!("{base_url}/stats"))
+            .query(&[("group_by", group_by), ("days", &days.to_string())])
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {api_token}"))
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                if status != StatusCode::OK {
+                    let text = response.text().await.unwrap_or(String::new());
+                    return Err(Error::Request(Some(status), text));
+                }
+
+                response
+                    .json::<StatsResponse>()
+                    .await
+                    .map_err(|_| Error::Deserialization)
+            }
+            Err(error) => Err(Error::Request(error.status(), error.to_string())),
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct TicketEntry {
+        pub ticket: String,
+        pub seconds: i64,
+        pub projects: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct TicketsResponse {
+        pub entries: Vec<TicketEntry>,
+    }
+
+    pub async fn get_tickets(
+        reqwest: &reqwest::Client,
+        base_url: &str,
+        api_token: &str,
+        project: Option<&str>,
+        days: i64,
+    ) -> Result<TicketsResponse, Error> {
+        let mut query = vec![("days", days.to_string())];
+        if let Some(project) = project {
+            query.push(("project", project.to_owned()));
+        }
+
+        let result = reqwest
+            .get(format!("{base_url}/reports/tickets"))
+            .query(&query)
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {api_token}"))
+            .send()
+            .await;
+
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                if status != StatusCode::OK {
+                    let text = response.text().await.unwrap_or(String::new());
+                    return Err(Error::Request(Some(status), text));
+                }
+
+                response
+                    .json::<TicketsResponse>()
+                    .await
+                    .map_err(|_| Error::Deserialization)
+            }
+            Err(error) => Err(Error::Request(error.status(), error.to_string())),
+        }
+    }
 }
 
 pub mod github {
@@ -16,7 +16,7 @@ pub async fn github_login(reqwest: &reqwest::Client, base_url: &str) -> anyhow::
             break;
         }
 
-        match crate::clients::github::get_user_authorized(&reqwest, &user_codes.device_code).await {
+        match crate::clients::github::get_user_authorized(reqwest, &user_codes.device_code).await {
             Ok(res) => match res {
                 GetUserAuthorizedResponse::Success { access_token } => {
                     let login_response = crate::clients::cairos::login(
@@ -40,3 +40,25 @@ pub async fn github_login(reqwest: &reqwest::Client, base_url: &str) -> anyhow::
 
     Ok(())
 }
+
+/// Revokes the saved token on the server, then forgets it locally.
+pub async fn logout(
+    reqwest: &reqwest::Client,
+    base_url: &str,
+    token: Option<&str>,
+) -> anyhow::Result<()> {
+    let Some(token) = token else {
+        println!("You are not logged in");
+        return Ok(());
+    };
+
+    // A token the server already dropped is still removed locally.
+    if let Err(error) = crate::clients::cairos::logout(reqwest, base_url, token).await {
+        eprintln!("Could not revoke the token on the server: {error}");
+    }
+
+    super::config::remove_token()?;
+    println!("Logged out");
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::config::{Config, get_config_file_path};
+use crate::config::{Classification, Config, Heartbeat, Privacy, Terminal, get_config_file_path};
 use anyhow::Context;
 use std::fs;
 
@@ -12,6 +12,12 @@ pub fn setup(base_url: String) -> anyhow::Result<()> {
     let default = Config {
         base_url,
         token: None,
+        privacy: Privacy::default(),
+        exclude: Vec::new(),
+        heartbeat: Heartbeat::default(),
+        classification: Classification::default(),
+        terminal: Terminal::default(),
+        categories: Vec::new(),
     };
 
     let toml_str =
@@ -22,11 +28,19 @@ pub fn setup(base_url: String) -> anyhow::Result<()> {
 }
 
 pub fn set_token(token: String) -> anyhow::Result<()> {
+    update(|config| config.token = Some(token))
+}
+
+pub fn remove_token() -> anyhow::Result<()> {
+    update(|config| config.token = None)
+}
+
+fn update(change: impl FnOnce(&mut Config)) -> anyhow::Result<()> {
     let config_file = get_config_file_path().context("Config file not found")?;
     let mut config: Config = toml::from_str(&fs::read_to_string(&config_file)?)
         .context("failed to parse config file")?;
 
-    config.token = Some(token);
+    change(&mut config);
 
     let toml_str = toml::to_string_pretty(&config).context("failed to serialize config")?;
     fs::write(&config_file, toml_str).context("failed to write config file")?;
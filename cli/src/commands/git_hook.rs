@@ -0,0 +1,203 @@
+use super::heartbeat::uri_of;
+use crate::{
+    clients::cairos::{CommitKind, SendCommitParams},
+    config::{Config, Privacy, exclude_set},
+};
+use anyhow::Context;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use time::format_description::well_known::Rfc3339;
+
+/// Identifies the hooks written by `install`, which may be overwritten.
+const MARKER: &str = "# Installed by `cairos git-hook install`";
+
+const HOOKS: &[&str] = &["post-commit", "post-checkout"];
+
+/// Writes the `post-commit` and `post-checkout` hooks of the repository at
+/// `repo`, leaving hooks from other tools alone.
+pub async fn install(repo: &Path) -> anyhow::Result<()> {
+    let hooks_dir = PathBuf::from(
+        git(
+            repo,
+            &["rev-parse", "--path-format=absolute", "--git-path", "hooks"],
+        )
+        .await?,
+    );
+    fs::create_dir_all(&hooks_dir).context("failed to create hooks directory")?;
+
+    let binary = std::env::current_exe().context("failed to locate the cairos binary")?;
+    let binary = binary.to_string_lossy().replace('\'', r"'\''");
+
+    // All checked first, so a foreign hook doesn't leave the others installed.
+    for hook in HOOKS {
+        let path = hooks_dir.join(hook);
+        if let Ok(existing) = fs::read_to_string(&path)
+            && !existing.contains(MARKER)
+        {
+            anyhow::bail!(
+                "{} already exists, add `cairos git-hook {hook} \"$@\"` to it instead",
+                path.display()
+            );
+        }
+    }
+
+    for hook in HOOKS {
+        let path = hooks_dir.join(hook);
+        // In the background, so committing never waits for the API.
+        let script =
+            format!("#!/bin/sh\n{MARKER}\n'{binary}' git-hook {hook} \"$@\" >/dev/null 2>&1 &\n");
+        fs::write(&path, script).with_context(|| format!("failed to write {}", path.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .with_context(|| format!("failed to make {} executable", path.display()))?;
+        }
+    }
+
+    println!(
+        "Installed {} in {}",
+        HOOKS.join(" and "),
+        hooks_dir.display()
+    );
+
+    Ok(())
+}
+
+/// Sends the commit just made in the current repository.
+pub async fn post_commit(reqwest: &reqwest::Client, config: &Config) -> anyhow::Result<()> {
+    send(reqwest, config, CommitKind::Commit).await
+}
+
+/// Sends the branch checked out, `flag` being `1` for a branch checkout and
+/// `0` for files checked out, which are ignored.
+pub async fn post_checkout(
+    reqwest: &reqwest::Client,
+    config: &Config,
+    flag: &str,
+) -> anyhow::Result<()> {
+    if flag != "1" {
+        return Ok(());
+    }
+
+    send(reqwest, config, CommitKind::Checkout).await
+}
+
+async fn send(reqwest: &reqwest::Client, config: &Config, kind: CommitKind) -> anyhow::Result<()> {
+    let token = config
+        .token
+        .as_deref()
+        .context("You are not authenticated, run `cairos auth login --github` first")?;
+
+    let cwd = std::env::current_dir().context("failed to get the current directory")?;
+    let root = PathBuf::from(git(&cwd, &["rev-parse", "--show-toplevel"]).await?);
+    let exclude = exclude_set(&config.exclude)?;
+    if exclude.is_match(uri_of(&root)?) {
+        return Ok(());
+    }
+    let project = root
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("repository has no name")?
+        .to_owned();
+
+    let sha = git(&root, &["rev-parse", "HEAD"]).await?;
+    let branch = if config.privacy.keeps_branch() {
+        git(&root, &["symbolic-ref", "--short", "-q", "HEAD"])
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    let mut commit = SendCommitParams {
+        project,
+        sha,
+        kind,
+        branch,
+        message: None,
+        author_name: None,
+        author_email: None,
+        files: Vec::new(),
+        authored_at: None,
+        timestamp: time::OffsetDateTime::now_utc(),
+    };
+
+    if let CommitKind::Commit = kind {
+        let log = git(
+            &root,
+            &["log", "-1", "--format=%an%x00%ae%x00%aI%x00%cI%x00%s"],
+        )
+        .await?;
+        let mut fields = log.split('\0');
+        commit.author_name = fields.next().map(str::to_owned);
+        commit.author_email = fields.next().map(str::to_owned);
+        // Rebased and cherry-picked commits keep their author date, which the
+        // API only takes within a week.
+        commit.authored_at = fields
+            .next()
+            .and_then(|date| time::OffsetDateTime::parse(date, &Rfc3339).ok())
+            .filter(|date| commit.timestamp - *date < time::Duration::days(7));
+        if let Some(date) = fields.next() {
+            commit.timestamp =
+                time::OffsetDateTime::parse(date, &Rfc3339).context("invalid commit date")?;
+        }
+        commit.message = fields
+            .next()
+            .filter(|_| config.privacy.keeps_branch())
+            .map(str::to_owned);
+
+        let files = git(
+            &root,
+            &[
+                "diff-tree",
+                "--no-commit-id",
+                "--name-only",
+                "-r",
+                "-z",
+                "--root",
+                "HEAD",
+            ],
+        )
+        .await?;
+        commit.files = files
+            .split('\0')
+            .filter(|file| !file.is_empty())
+            .filter(|file| uri_of(&root.join(file)).is_ok_and(|uri| !exclude.is_match(uri)))
+            .filter_map(|file| match config.privacy {
+                Privacy::ProjectOnly => None,
+                privacy => Some(privacy.redact(file)),
+            })
+            .collect();
+    }
+
+    crate::clients::cairos::send_commit(reqwest, &config.base_url, token, &commit).await?;
+
+    Ok(())
+}
+
+/// Output of `git args` run in `dir`, without the trailing newline.
+async fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8(output.stdout)
+        .context("git output is not UTF-8")?
+        .trim_end_matches('\n')
+        .to_owned())
+}
@@ -0,0 +1,224 @@
+use crate::{
+    category::Categorizer,
+    clients::cairos::{EventKind, SendEventsParams},
+    config::{Config, SentHeartbeats, exclude_set, get_config_file_path},
+    machine::Machine,
+};
+use anyhow::Context;
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+/// Heartbeats kept on disk while the API is unreachable, older ones are
+/// dropped past it.
+const CAPACITY: usize = 1024;
+
+/// Activity reported by an editor or script through `cairos heartbeat`.
+pub struct Heartbeat {
+    pub file: PathBuf,
+    pub language: Option<String>,
+    pub is_write: bool,
+    /// 1-based, as editors show it
+    pub line: Option<u32>,
+    pub project: Option<String>,
+    pub editor: Option<String>,
+}
+
+/// Sends `heartbeat` with the rules of the language server: exclusions,
+/// debounce, privacy and queueing, along with the ones still queued from
+/// previous runs.
+pub async fn send(
+    reqwest: &reqwest::Client,
+    config: &Config,
+    heartbeat: Heartbeat,
+) -> anyhow::Result<()> {
+    let token = config
+        .token
+        .as_deref()
+        .context("You are not authenticated, run `cairos auth login --github` first")?;
+
+    let path = std::path::absolute(&heartbeat.file)
+        .with_context(|| format!("invalid file {}", heartbeat.file.display()))?;
+    let uri = uri_of(&path)?;
+
+    if exclude_set(&config.exclude)?.is_match(&uri) {
+        return Ok(());
+    }
+
+    // Kept on disk for the debounce to outlive each invocation.
+    let state_path = state_file("sent_heartbeats")?;
+    let now = time::OffsetDateTime::now_utc();
+    let mut sent: SentHeartbeats = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    if !config
+        .heartbeat
+        .is_due(&sent, &uri, heartbeat.is_write, now)
+    {
+        return Ok(());
+    }
+
+    let categorizer = Categorizer::new(&config.categories)?;
+    let root = crate::git::repository_root(&path).and_then(|root| uri_of(root).ok());
+    let language = match &heartbeat.language {
+        Some(language) => crate::language::normalize(language),
+        None => crate::language::detect(&uri, first_line(&path).as_deref()),
+    };
+    let project = heartbeat
+        .project
+        .or_else(|| crate::git::repository_name(&path));
+    let keeps_position = config.privacy.keeps_position();
+    let machine = Machine::load().ok();
+
+    let event = SendEventsParams {
+        uri: config.privacy.redact(&uri),
+        timestamp: now,
+        is_write: heartbeat.is_write,
+        language,
+        line_number: heartbeat
+            .line
+            .filter(|_| keeps_position)
+            .map(|line| i32::try_from(line.saturating_sub(1)).unwrap_or(i32::MAX)),
+        cursor_pos: None,
+        project,
+        editor: heartbeat.editor,
+        editor_version: None,
+        client_version: env!("CARGO_PKG_VERSION").to_owned(),
+        os: std::env::consts::OS.to_owned(),
+        arch: std::env::consts::ARCH.to_owned(),
+        machine_id: machine.as_ref().map(|m| m.id.clone()),
+        hostname: machine.as_ref().and_then(|m| m.hostname.clone()),
+        lines_added: 0,
+        lines_removed: 0,
+        typed_chars: 0,
+        pasted_chars: 0,
+        bulk_chars: 0,
+        category: categorizer
+            .categorize(&uri, root.as_deref())
+            .as_str()
+            .to_owned(),
+        kind: EventKind::Editor,
+        command: None,
+        duration_seconds: None,
+        branch: config
+            .privacy
+            .keeps_branch()
+            .then(|| crate::git::branch(&path))
+            .flatten(),
+    };
+
+    deliver(reqwest, config, token, event).await?;
+
+    sent.record(&uri, now, &config.heartbeat);
+    fs::write(&state_path, serde_json::to_string(&sent)?)
+        .context("failed to write sent heartbeats")?;
+
+    Ok(())
+}
+
+/// Sends `event` after the ones queued by previous runs, keeping on disk
+/// those the API couldn't take yet.
+pub(super) async fn deliver(
+    reqwest: &reqwest::Client,
+    config: &Config,
+    token: &str,
+    event: SendEventsParams,
+) -> anyhow::Result<()> {
+    let queue_path = state_file("heartbeat_queue")?;
+    // Held until the queue is saved, as hooks may run several at once.
+    let lock = File::create(state_file("heartbeat_queue.lock")?)
+        .context("failed to open heartbeat queue lock")?;
+    lock.lock().context("failed to lock heartbeat queue")?;
+
+    let mut queue = load_queue(&queue_path);
+    if !queue
+        .back_mut()
+        .is_some_and(|previous| previous.merge(&event))
+    {
+        queue.push_back(event);
+    }
+    while queue.len() > CAPACITY {
+        queue.pop_front();
+    }
+
+    let mut failed = None;
+    while let Some(event) = queue.front() {
+        match crate::clients::cairos::send_events(reqwest, &config.base_url, token, event).await {
+            Ok(()) => {}
+            Err(e) if e.is_retryable() => {
+                failed = Some(e);
+                break;
+            }
+            Err(e) => eprintln!("Error when trying to send events: {e}"),
+        }
+        queue.pop_front();
+    }
+
+    save_queue(&queue_path, &queue)?;
+
+    if let Some(e) = failed {
+        eprintln!("Cairos is unreachable, {} events queued: {e}", queue.len());
+    }
+
+    Ok(())
+}
+
+/// Path of the absolute `path` in the form the language server sends, so
+/// both dedupe and group the same way.
+pub(super) fn uri_of(path: &Path) -> anyhow::Result<String> {
+    let url = url::Url::from_file_path(path)
+        .map_err(|()| anyhow::anyhow!("invalid path {}", path.display()))?;
+
+    Ok(url[url::Position::BeforeUsername..].to_owned())
+}
+
+/// File kept next to the config file.
+fn state_file(name: &str) -> anyhow::Result<PathBuf> {
+    let path = get_config_file_path()
+        .context("Config directory not found")?
+        .with_file_name(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create config directory")?;
+    }
+
+    Ok(path)
+}
+
+/// Queued events, one JSON object per line, skipping unreadable ones.
+fn load_queue(path: &Path) -> VecDeque<SendEventsParams> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return VecDeque::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Writes next to `path` then renames, so a crash never leaves half a queue.
+fn save_queue(path: &Path, queue: &VecDeque<SendEventsParams>) -> anyhow::Result<()> {
+    let mut content = String::new();
+    for event in queue {
+        content.push_str(&serde_json::to_string(event)?);
+        content.push('\n');
+    }
+
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, content).context("failed to write heartbeat queue")?;
+    fs::rename(&temporary, path).context("failed to replace heartbeat queue")
+}
+
+/// For the shebang, when the file name doesn't tell the language.
+fn first_line(path: &Path) -> Option<String> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_line(&mut line)
+        .ok()?;
+
+    Some(line)
+}
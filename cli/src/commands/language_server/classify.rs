@@ -0,0 +1,86 @@
+use crate::config::Classification;
+use std::time::Duration;
+
+/// How an insertion most likely got into a document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Typed,
+    Pasted,
+    /// Large insertions or fast streams of them, typical of AI assistants
+    Bulk,
+}
+
+/// Non-whitespace characters inserted, the size thresholds apply to.
+pub fn size(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+/// Classifies an insertion of `size` characters, `since_previous` being the
+/// time elapsed since the previous insertion not counted as typed.
+pub fn classify(config: &Classification, size: usize, since_previous: Option<Duration>) -> Origin {
+    if size <= config.typed_max_chars {
+        return Origin::Typed;
+    }
+    if size >= config.bulk_min_chars {
+        return Origin::Bulk;
+    }
+
+    let streamed = since_previous.is_some_and(|elapsed| {
+        size as f64 / elapsed.as_secs_f64().max(f64::EPSILON) > config.bulk_chars_per_second
+    });
+
+    if streamed {
+        Origin::Bulk
+    } else {
+        Origin::Pasted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Classification {
+        Classification {
+            typed_max_chars: 16,
+            bulk_min_chars: 400,
+            bulk_chars_per_second: 100.0,
+        }
+    }
+
+    #[test]
+    fn size_ignores_whitespace_and_counts_characters() {
+        assert_eq!(size("  \n\t"), 0);
+        assert_eq!(size("a b\r\nc"), 3);
+        assert_eq!(size("héllo 😀"), 6);
+    }
+
+    #[test]
+    fn classify_by_size_thresholds() {
+        let config = config();
+
+        assert_eq!(classify(&config, 1, None), Origin::Typed);
+        assert_eq!(classify(&config, 16, None), Origin::Typed);
+        assert_eq!(classify(&config, 17, None), Origin::Pasted);
+        assert_eq!(classify(&config, 399, None), Origin::Pasted);
+        assert_eq!(classify(&config, 400, None), Origin::Bulk);
+    }
+
+    #[test]
+    fn classify_fast_streams_as_bulk() {
+        let config = config();
+
+        // 100 characters in a second is exactly the limit, not above it.
+        assert_eq!(
+            classify(&config, 100, Some(Duration::from_secs(1))),
+            Origin::Pasted
+        );
+        assert_eq!(
+            classify(&config, 101, Some(Duration::from_secs(1))),
+            Origin::Bulk
+        );
+        assert_eq!(classify(&config, 17, Some(Duration::ZERO)), Origin::Bulk);
+        // Typed insertions stay typed however close they are.
+        assert_eq!(classify(&config, 16, Some(Duration::ZERO)), Origin::Typed);
+    }
+}
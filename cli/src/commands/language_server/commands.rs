@@ -0,0 +1,156 @@
+use super::CairosLanguangeServer;
+use crate::format::format_duration;
+use std::sync::atomic::Ordering;
+use tower_lsp::{
+    jsonrpc::{Error, Result},
+    lsp_types::{MessageType, ShowDocumentParams, Url},
+};
+
+pub const SHOW_TODAY: &str = "cairos.showToday";
+pub const SHOW_PROJECT: &str = "cairos.showProject";
+pub const PAUSE: &str = "cairos.pause";
+pub const OPEN_DASHBOARD: &str = "cairos.openDashboard";
+
+/// Advertised in `execute_command_provider`, for editors to bind to keys.
+pub const ALL: &[&str] = &[SHOW_TODAY, SHOW_PROJECT, PAUSE, OPEN_DASHBOARD];
+
+/// Projects listed after today's total.
+const TOP_PROJECTS: usize = 3;
+
+impl CairosLanguangeServer {
+    /// Runs `command`, replying to the user with a message or a document.
+    pub(super) async fn execute(&self, command: &str, argument: Option<&str>) -> Result<()> {
+        match command {
+            SHOW_TODAY => self.show_today().await,
+            SHOW_PROJECT => self.show_project(argument).await,
+            PAUSE => self.toggle_pause().await,
+            OPEN_DASHBOARD => self.open_dashboard().await,
+            _ => return Err(Error::invalid_params(format!("Unknown command {command}"))),
+        }
+
+        Ok(())
+    }
+
+    async fn show_today(&self) {
+        let Some(stats) = self.today_stats().await else {
+            return;
+        };
+
+        let projects: Vec<String> = stats
+            .entries
+            .iter()
+            .filter_map(|entry| Some((entry.key.as_deref()?, entry.seconds)))
+            .take(TOP_PROJECTS)
+            .map(|(project, seconds)| format!("{project} {}", format_duration(seconds)))
+            .collect();
+
+        let mut message = format!("Today: {}", format_duration(stats.total_seconds));
+        if !projects.is_empty() {
+            message.push_str(&format!(" ({})", projects.join(", ")));
+        }
+
+        self.client.show_message(MessageType::INFO, message).await;
+    }
+
+    /// Today's time in the project named by `argument`, or in the project of
+    /// the last file worked on.
+    async fn show_project(&self, argument: Option<&str>) {
+        let project = match argument {
+            Some(project) => Some(project.to_owned()),
+            None => {
+                let uri = self.current_file.lock().await.uri.clone();
+                self.project_for(&uri).await
+            }
+        };
+        let Some(project) = project else {
+            self.client
+                .show_message(MessageType::INFO, "No project to show, open a file first")
+                .await;
+            return;
+        };
+
+        let Some(stats) = self.today_stats().await else {
+            return;
+        };
+        let seconds = stats
+            .entries
+            .iter()
+            .find(|entry| entry.key.as_deref() == Some(project.as_str()))
+            .map_or(0, |entry| entry.seconds);
+
+        self.client
+            .show_message(
+                MessageType::INFO,
+                format!("{project} today: {}", format_duration(seconds)),
+            )
+            .await;
+    }
+
+    async fn toggle_pause(&self) {
+        let paused = !self.paused.fetch_xor(true, Ordering::Relaxed);
+        let message = if paused {
+            "Cairos tracking paused"
+        } else {
+            "Cairos tracking resumed"
+        };
+
+        self.client.show_message(MessageType::INFO, message).await;
+    }
+
+    async fn open_dashboard(&self) {
+        let base_url = self
+            .api
+            .read()
+            .expect("API settings lock poisoned")
+            .base_url
+            .clone();
+        let url = format!("{base_url}/dashboard");
+
+        let shown = match Url::parse(&url) {
+            Ok(uri) => self
+                .client
+                .show_document(ShowDocumentParams {
+                    uri,
+                    external: Some(true),
+                    take_focus: Some(true),
+                    selection: None,
+                })
+                .await
+                .unwrap_or(false),
+            Err(_) => false,
+        };
+
+        // Not every client supports `window/showDocument`.
+        if !shown {
+            self.client
+                .show_message(MessageType::INFO, format!("Cairos dashboard: {url}"))
+                .await;
+        }
+    }
+
+    /// Today's time per project, reporting errors to the user.
+    async fn today_stats(&self) -> Option<crate::clients::cairos::StatsResponse> {
+        let api = self.api.read().expect("API settings lock poisoned").clone();
+        let result = crate::clients::cairos::get_stats(
+            &self.http_client,
+            &api.base_url,
+            api.token.as_deref().unwrap_or_default(),
+            "project",
+            1,
+        )
+        .await;
+
+        match result {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                self.client
+                    .show_message(
+                        MessageType::ERROR,
+                        format!("Error when trying to get stats: {e}"),
+                    )
+                    .await;
+                None
+            }
+        }
+    }
+}
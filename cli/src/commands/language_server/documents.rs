@@ -0,0 +1,315 @@
+use super::classify::{self, Origin};
+use crate::config::Classification;
+use similar::{ChangeTag, TextDiff};
+use std::{collections::HashMap, time::Instant};
+use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent};
+
+/// Changes made to a document since its last heartbeat.
+#[derive(Default)]
+pub struct Churn {
+    pub added: i32,
+    pub removed: i32,
+    /// Non-whitespace characters inserted, by origin
+    pub typed_chars: i32,
+    pub pasted_chars: i32,
+    pub bulk_chars: i32,
+}
+
+struct Document {
+    text: String,
+    /// Canonical language, from the editor or detected when it had none
+    language: Option<String>,
+    /// Text when the last heartbeat for the document was sent
+    baseline: String,
+    /// Characters inserted since the last heartbeat, lines are diffed instead
+    inserted: Churn,
+    /// Last insertion not counted as typed
+    last_insertion: Option<Instant>,
+}
+
+/// Text of the open documents, kept in sync through incremental changes.
+pub struct Documents {
+    documents: HashMap<String, Document>,
+    classification: Classification,
+}
+
+impl Documents {
+    pub fn new(classification: Classification) -> Self {
+        Self {
+            documents: HashMap::new(),
+            classification,
+        }
+    }
+
+    pub fn open(&mut self, uri: String, text: String, language_id: &str) {
+        let language = crate::language::normalize(language_id)
+            .or_else(|| crate::language::detect(&uri, Some(&text)));
+
+        self.documents.insert(
+            uri,
+            Document {
+                baseline: text.clone(),
+                text,
+                language,
+                inserted: Churn::default(),
+                last_insertion: None,
+            },
+        );
+    }
+
+    pub fn close(&mut self, uri: &str) {
+        self.documents.remove(uri);
+    }
+
+    /// Closes `path` and, when it is a folder, the documents below it.
+    pub fn delete(&mut self, path: &str) {
+        self.documents.retain(|uri, _| !super::is_within(path, uri));
+    }
+
+    /// Moves `from` and, when it is a folder, the documents below it to `to`.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        let moved: Vec<String> = self
+            .documents
+            .keys()
+            .filter(|uri| super::is_within(from, uri))
+            .cloned()
+            .collect();
+
+        for uri in moved {
+            if let Some(document) = self.documents.remove(&uri) {
+                self.documents
+                    .insert(format!("{to}{}", &uri[from.len()..]), document);
+            }
+        }
+    }
+
+    /// Whether `uri` changed since its last heartbeat.
+    pub fn has_changes(&self, uri: &str) -> bool {
+        self.documents.get(uri).is_some_and(|document| {
+            let inserted = &document.inserted;
+            document.text != document.baseline
+                || inserted.typed_chars + inserted.pasted_chars + inserted.bulk_chars > 0
+        })
+    }
+
+    /// Language of `uri`, detected from its name when it isn't open.
+    pub fn language(&self, uri: &str) -> Option<String> {
+        match self.documents.get(uri) {
+            Some(document) => document.language.clone(),
+            None => crate::language::detect(uri, None),
+        }
+    }
+
+    pub fn change(&mut self, uri: &str, changes: &[TextDocumentContentChangeEvent]) {
+        let Some(document) = self.documents.get_mut(uri) else {
+            return;
+        };
+
+        let now = Instant::now();
+
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let start = offset_at(&document.text, range.start);
+                    let end = offset_at(&document.text, range.end).max(start);
+                    document.text.replace_range(start..end, &change.text);
+                }
+                // Whole document replaced, nothing tells how it was edited.
+                None => {
+                    document.text = change.text.clone();
+                    continue;
+                }
+            }
+
+            let size = classify::size(&change.text);
+            if size == 0 {
+                continue;
+            }
+
+            let since_previous = document.last_insertion.map(|at| now - at);
+            let count = i32::try_from(size).unwrap_or(i32::MAX);
+
+            match classify::classify(&self.classification, size, since_previous) {
+                Origin::Typed => document.inserted.typed_chars += count,
+                Origin::Pasted => document.inserted.pasted_chars += count,
+                Origin::Bulk => document.inserted.bulk_chars += count,
+            }
+            if size > self.classification.typed_max_chars {
+                document.last_insertion = Some(now);
+            }
+        }
+    }
+
+    /// Changes since the previous call for `uri`, diffing lines and adding up
+    /// the insertions by origin.
+    pub fn take_churn(&mut self, uri: &str) -> Churn {
+        let Some(document) = self.documents.get_mut(uri) else {
+            return Churn::default();
+        };
+
+        let mut churn = std::mem::take(&mut document.inserted);
+        if document.text == document.baseline {
+            return churn;
+        }
+
+        for change in TextDiff::from_lines(&document.baseline, &document.text).iter_all_changes() {
+            match change.tag() {
+                ChangeTag::Insert => churn.added += 1,
+                ChangeTag::Delete => churn.removed += 1,
+                ChangeTag::Equal => {}
+            }
+        }
+
+        document.baseline = document.text.clone();
+        churn
+    }
+}
+
+/// Byte offset of an LSP position, whose character counts UTF-16 code units.
+/// Positions past the end of a line, before any `\r\n`, or of the text are
+/// clamped.
+fn offset_at(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line = &text[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    let line = line.strip_suffix('\r').unwrap_or(line);
+
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + offset;
+        }
+        units += c.len_utf16();
+    }
+
+    line_start + line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Range;
+
+    const URI: &str = "file:///project/src/main.rs";
+
+    fn position(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    fn edit(start: Position, end: Position, text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range { start, end }),
+            range_length: None,
+            text: text.to_owned(),
+        }
+    }
+
+    fn opened(text: &str) -> Documents {
+        let mut documents = Documents::new(Classification::default());
+        documents.open(URI.to_owned(), text.to_owned(), "rust");
+        documents
+    }
+
+    #[test]
+    fn offset_at_counts_utf16_units() {
+        let text = "aé😀b\nc";
+
+        assert_eq!(offset_at(text, position(0, 0)), 0);
+        assert_eq!(offset_at(text, position(0, 2)), "aé".len());
+        // The emoji takes two UTF-16 units and four bytes.
+        assert_eq!(offset_at(text, position(0, 4)), "aé😀".len());
+        assert_eq!(offset_at(text, position(1, 1)), text.len());
+    }
+
+    #[test]
+    fn offset_at_clamps_past_the_end() {
+        let text = "ab\ncd";
+
+        assert_eq!(offset_at(text, position(0, 10)), 2);
+        assert_eq!(offset_at(text, position(1, 10)), text.len());
+        assert_eq!(offset_at(text, position(5, 0)), text.len());
+    }
+
+    #[test]
+    fn offset_at_stops_before_crlf() {
+        let text = "ab\r\ncd\r\n";
+
+        assert_eq!(offset_at(text, position(0, 2)), 2);
+        assert_eq!(offset_at(text, position(0, 10)), 2);
+        assert_eq!(offset_at(text, position(1, 0)), 4);
+        assert_eq!(offset_at(text, position(2, 0)), text.len());
+    }
+
+    #[test]
+    fn change_applies_range_edits() {
+        let mut documents = opened("fn main() {}\n");
+
+        documents.change(
+            URI,
+            &[
+                edit(position(0, 11), position(0, 11), "\n    println!();\n"),
+                // At the end of the document, on the line after the last newline.
+                edit(position(3, 0), position(3, 0), "// end\n"),
+            ],
+        );
+
+        assert_eq!(
+            documents.documents[URI].text,
+            "fn main() {\n    println!();\n}\n// end\n"
+        );
+    }
+
+    #[test]
+    fn change_keeps_crlf_line_endings() {
+        let mut documents = opened("a\r\nb\r\n");
+
+        documents.change(URI, &[edit(position(0, 5), position(0, 5), "x")]);
+
+        assert_eq!(documents.documents[URI].text, "ax\r\nb\r\n");
+    }
+
+    #[test]
+    fn take_churn_diffs_lines_since_the_previous_call() {
+        let mut documents = opened("one\ntwo\nthree\n");
+
+        documents.change(
+            URI,
+            &[
+                edit(position(1, 0), position(2, 0), ""),
+                edit(position(1, 0), position(1, 5), "tres\nfour"),
+            ],
+        );
+        let churn = documents.take_churn(URI);
+
+        assert_eq!((churn.added, churn.removed), (2, 2));
+        assert!(!documents.has_changes(URI));
+
+        let churn = documents.take_churn(URI);
+        assert_eq!((churn.added, churn.removed), (0, 0));
+    }
+
+    #[test]
+    fn take_churn_counts_insertions_by_origin() {
+        let mut documents = opened("");
+
+        documents.change(URI, &[edit(position(0, 0), position(0, 0), "let x = 1;")]);
+        let churn = documents.take_churn(URI);
+
+        assert_eq!(churn.typed_chars, "letx=1;".len() as i32);
+        assert_eq!(churn.pasted_chars + churn.bulk_chars, 0);
+    }
+
+    #[test]
+    fn take_churn_of_unknown_document_is_empty() {
+        let churn = opened("").take_churn("file:///elsewhere.rs");
+
+        assert_eq!((churn.added, churn.removed, churn.typed_chars), (0, 0, 0));
+    }
+}
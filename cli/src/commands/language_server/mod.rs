@@ -0,0 +1,553 @@
+use crate::{
+    category::Categorizer,
+    clients::cairos::EventKind,
+    config::{Config, Privacy, SentHeartbeats},
+    machine::Machine,
+};
+use documents::Documents;
+use sender::{Request, Sender};
+use settings::{Api, Options, Settings};
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicBool, Ordering},
+};
+use tokio::sync::Mutex;
+use tower_lsp::{
+    Client, LanguageServer, LspService, Server,
+    jsonrpc::Result,
+    lsp_types::{
+        CreateFilesParams, DeleteFilesParams, DidChangeConfigurationParams,
+        DidChangeTextDocumentParams, DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams,
+        DidOpenTextDocumentParams, DidSaveTextDocumentParams, ExecuteCommandOptions,
+        ExecuteCommandParams, FileOperationFilter, FileOperationPattern, FileOperationPatternKind,
+        FileOperationRegistrationOptions, InitializeParams, InitializeResult, InitializedParams,
+        MessageType, OneOf, RenameFilesParams, ServerCapabilities, ServerInfo,
+        TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+        WorkspaceFileOperationsServerCapabilities, WorkspaceFolder,
+        WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities,
+    },
+};
+
+mod classify;
+mod commands;
+mod documents;
+mod sender;
+mod settings;
+
+const NAME: &str = env!("CARGO_PKG_NAME");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+struct Event {
+    uri: String,
+    is_write: bool,
+    line_number: Option<i32>,
+    cursor_pos: Option<i32>,
+}
+
+/// Workspace folder, whose name is reported as the project of its files.
+#[derive(Clone)]
+struct Project {
+    root: String,
+    name: String,
+}
+
+impl From<WorkspaceFolder> for Project {
+    fn from(folder: WorkspaceFolder) -> Self {
+        Self {
+            root: path_of(&folder.uri).trim_end_matches('/').to_owned(),
+            name: folder.name,
+        }
+    }
+}
+
+/// Path of a document, as sent to the API.
+fn path_of(uri: &Url) -> &str {
+    &uri[url::Position::BeforeUsername..]
+}
+
+/// File at `uri`, as a path of the local file system.
+fn file_path(uri: &str) -> Option<std::path::PathBuf> {
+    Url::parse(&format!("file://{uri}"))
+        .ok()?
+        .to_file_path()
+        .ok()
+}
+
+/// Whether `uri` is the file or folder `path` or below it.
+fn is_within(path: &str, uri: &str) -> bool {
+    uri.strip_prefix(path)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Editor the server was started by, from `InitializeParams::client_info`.
+#[derive(Default)]
+struct Editor {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+pub struct CurrentFile {
+    uri: String,
+    sent: SentHeartbeats,
+}
+
+struct CairosLanguangeServer {
+    client: Client,
+    http_client: reqwest::Client,
+    sender: Sender,
+    /// Shared with the sender, editor settings can change it at any time
+    api: Arc<RwLock<Api>>,
+    current_file: Mutex<CurrentFile>,
+    projects: Mutex<Vec<Project>>,
+    editor: Mutex<Editor>,
+    machine: Option<Machine>,
+    documents: Mutex<Documents>,
+    categorizer: Categorizer,
+    /// Set by `cairos.pause`, no events are sent until it is run again
+    paused: AtomicBool,
+    /// From config.toml, before editor settings are applied
+    config: Config,
+    options: Mutex<Options>,
+}
+
+impl CairosLanguangeServer {
+    /// Applies editor settings over config.toml, replacing the previous ones.
+    async fn apply_settings(&self, value: serde_json::Value) {
+        let resolved = Settings::from_value(value)
+            .and_then(|settings| Ok((settings.api(&self.config), settings.options(&self.config)?)));
+
+        match resolved {
+            Ok((api, options)) => {
+                *self.api.write().expect("API settings lock poisoned") = api;
+                *self.options.lock().await = options;
+            }
+            Err(e) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Invalid Cairos settings: {e:#}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    fn is_authenticated(&self) -> bool {
+        self.api
+            .read()
+            .expect("API settings lock poisoned")
+            .token
+            .is_some()
+    }
+
+    /// Name of the workspace folder of `uri`.
+    async fn project_for(&self, uri: &str) -> Option<String> {
+        self.workspace_for(uri).await.map(|p| p.name)
+    }
+
+    /// Innermost workspace folder containing `uri`.
+    async fn workspace_for(&self, uri: &str) -> Option<Project> {
+        self.projects
+            .lock()
+            .await
+            .iter()
+            .filter(|p| uri != p.root && is_within(&p.root, uri))
+            .max_by_key(|p| p.root.len())
+            .cloned()
+    }
+
+    async fn send(&self, event: Event) {
+        if self.paused.load(Ordering::Relaxed) || !self.is_authenticated() {
+            return;
+        }
+
+        let options = self.options.lock().await;
+        if options.exclude.is_match(&event.uri) {
+            return;
+        }
+        let privacy = options.privacy;
+        let heartbeat = options.heartbeat.clone();
+        drop(options);
+
+        let now = time::OffsetDateTime::now_utc();
+        {
+            // Released before anything slow, other notifications wait on it.
+            let mut current_file = self.current_file.lock().await;
+            if !heartbeat.is_due(&current_file.sent, &event.uri, event.is_write, now) {
+                return;
+            }
+            current_file.uri = event.uri.to_owned();
+            current_file.sent.record(&event.uri, now, &heartbeat);
+        }
+
+        let workspace = self.workspace_for(&event.uri).await;
+        let branch = match file_path(&event.uri).filter(|_| privacy.keeps_branch()) {
+            // Reads files up the folders of the document.
+            Some(path) => tokio::task::spawn_blocking(move || crate::git::branch(&path))
+                .await
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        let editor = self.editor.lock().await;
+        let keeps_position = privacy.keeps_position();
+        let (churn, language) = {
+            let mut documents = self.documents.lock().await;
+            (
+                documents.take_churn(&event.uri),
+                documents.language(&event.uri),
+            )
+        };
+
+        let queued = self.sender.send(Request::Capture(Box::new(
+            crate::clients::cairos::SendEventsParams {
+                uri: privacy.redact(&event.uri),
+                timestamp: now,
+                is_write: event.is_write,
+                language,
+                line_number: event.line_number.filter(|_| keeps_position),
+                cursor_pos: event.cursor_pos.filter(|_| keeps_position),
+                project: workspace.as_ref().map(|p| p.name.clone()),
+                editor: editor.name.clone(),
+                editor_version: editor.version.clone(),
+                client_version: VERSION.to_owned(),
+                os: std::env::consts::OS.to_owned(),
+                arch: std::env::consts::ARCH.to_owned(),
+                machine_id: self.machine.as_ref().map(|m| m.id.clone()),
+                hostname: self.machine.as_ref().and_then(|m| m.hostname.clone()),
+                lines_added: churn.added,
+                lines_removed: churn.removed,
+                typed_chars: churn.typed_chars,
+                pasted_chars: churn.pasted_chars,
+                bulk_chars: churn.bulk_chars,
+                category: self
+                    .categorizer
+                    .categorize(&event.uri, workspace.as_ref().map(|p| p.root.as_str()))
+                    .as_str()
+                    .to_owned(),
+                kind: EventKind::Editor,
+                command: None,
+                duration_seconds: None,
+                branch,
+            },
+        )));
+        if !queued {
+            self.client
+                .log_message(MessageType::WARNING, "Too many events queued, dropping one")
+                .await;
+        }
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for CairosLanguangeServer {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        if let Some(options) = params.initialization_options {
+            self.apply_settings(options).await;
+        }
+
+        if let Some(client_info) = params.client_info {
+            *self.editor.lock().await = Editor {
+                name: Some(client_info.name),
+                version: client_info.version,
+            };
+        }
+
+        #[allow(deprecated)]
+        let folders = params.workspace_folders.unwrap_or_else(|| {
+            params
+                .root_uri
+                .into_iter()
+                .map(|uri| WorkspaceFolder {
+                    name: uri
+                        .path_segments()
+                        .and_then(|mut s| s.next_back())
+                        .unwrap_or_default()
+                        .to_owned(),
+                    uri,
+                })
+                .collect()
+        });
+
+        *self.projects.lock().await = folders.into_iter().map(Project::from).collect();
+
+        // Folders too, renaming one moves every file below it.
+        let files = |matches| {
+            Some(FileOperationRegistrationOptions {
+                filters: vec![FileOperationFilter {
+                    scheme: Some("file".to_owned()),
+                    pattern: FileOperationPattern {
+                        glob: "**/*".to_owned(),
+                        matches,
+                        options: None,
+                    },
+                }],
+            })
+        };
+
+        Ok(InitializeResult {
+            server_info: Some(ServerInfo {
+                name: NAME.to_owned(),
+                version: Some(VERSION.to_owned()),
+            }),
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::INCREMENTAL,
+                )),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: commands::ALL.iter().map(|c| (*c).to_owned()).collect(),
+                    ..Default::default()
+                }),
+                workspace: Some(WorkspaceServerCapabilities {
+                    workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(OneOf::Left(true)),
+                    }),
+                    file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                        did_create: files(Some(FileOperationPatternKind::File)),
+                        did_rename: files(None),
+                        did_delete: files(None),
+                        ..Default::default()
+                    }),
+                }),
+                ..Default::default()
+            },
+        })
+    }
+
+    async fn initialized(&self, _params: InitializedParams) {
+        self.client
+            .log_message(MessageType::INFO, "Cairos language server initialized")
+            .await;
+
+        if !self.is_authenticated() {
+            self.client
+                .show_message(
+                    MessageType::WARNING,
+                    "Cairos isn't tracking: run `cairos auth login --github` or set a token in the editor settings",
+                )
+                .await;
+        }
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        self.sender.flush().await;
+        Ok(())
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        let argument = params.arguments.first().and_then(|a| a.as_str());
+        self.execute(&params.command, argument).await?;
+
+        Ok(None)
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        self.apply_settings(params.settings).await;
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let uri = path_of(&params.text_document.uri).to_owned();
+        self.documents.lock().await.open(
+            uri.clone(),
+            params.text_document.text,
+            &params.text_document.language_id,
+        );
+
+        let event = Event {
+            uri,
+            is_write: false,
+            line_number: None,
+            cursor_pos: None,
+        };
+
+        self.send(event).await
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        let uri = path_of(&params.text_document.uri).to_owned();
+        self.documents
+            .lock()
+            .await
+            .change(&uri, &params.content_changes);
+
+        let event = Event {
+            uri,
+            is_write: false,
+            line_number: params
+                .content_changes
+                .first()
+                .map_or_else(|| None, |c| c.range)
+                .map(|c| i32::try_from(c.start.line).unwrap_or(0)),
+            cursor_pos: params
+                .content_changes
+                .first()
+                .map_or_else(|| None, |c| c.range)
+                .map(|c| i32::try_from(c.start.character).unwrap_or(0)),
+        };
+
+        self.send(event).await
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let event = Event {
+            uri: path_of(&params.text_document.uri).to_owned(),
+            is_write: true,
+            line_number: None,
+            cursor_pos: None,
+        };
+
+        self.send(event).await
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = path_of(&params.text_document.uri);
+
+        let has_changes = self.documents.lock().await.has_changes(uri);
+
+        // Reopening starts a new session, sent without waiting for the interval.
+        {
+            let mut current_file = self.current_file.lock().await;
+            if current_file.uri == uri {
+                current_file.uri.clear();
+            }
+            // Pending changes would be lost with the document.
+            if has_changes {
+                current_file.sent.forget(uri);
+            }
+        }
+
+        if has_changes {
+            let event = Event {
+                uri: uri.to_owned(),
+                is_write: false,
+                line_number: None,
+                cursor_pos: None,
+            };
+            self.send(event).await;
+        }
+
+        self.documents.lock().await.close(uri);
+    }
+
+    async fn did_create_files(&self, params: CreateFilesParams) {
+        for file in params.files {
+            let Ok(uri) = Url::parse(&file.uri) else {
+                continue;
+            };
+            let event = Event {
+                uri: path_of(&uri).to_owned(),
+                is_write: true,
+                line_number: None,
+                cursor_pos: None,
+            };
+            self.send(event).await;
+        }
+    }
+
+    async fn did_rename_files(&self, params: RenameFilesParams) {
+        for file in params.files {
+            let (Ok(old_uri), Ok(new_uri)) = (Url::parse(&file.old_uri), Url::parse(&file.new_uri))
+            else {
+                continue;
+            };
+            let from = path_of(&old_uri).trim_end_matches('/');
+            let to = path_of(&new_uri).trim_end_matches('/');
+
+            self.documents.lock().await.rename(from, to);
+            {
+                let mut current_file = self.current_file.lock().await;
+                if is_within(from, &current_file.uri) {
+                    current_file.uri = format!("{to}{}", &current_file.uri[from.len()..]);
+                }
+            }
+
+            // History is only kept per full path.
+            if !matches!(self.options.lock().await.privacy, Privacy::Full) {
+                continue;
+            }
+
+            let queued = self.sender.send(Request::Rename(
+                crate::clients::cairos::RenameEventsParams {
+                    from: from.to_owned(),
+                    to: to.to_owned(),
+                },
+            ));
+            if !queued {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        "Too many events queued, dropping a rename",
+                    )
+                    .await;
+            }
+        }
+    }
+
+    async fn did_delete_files(&self, params: DeleteFilesParams) {
+        for file in params.files {
+            let Ok(uri) = Url::parse(&file.uri) else {
+                continue;
+            };
+            let path = path_of(&uri).trim_end_matches('/');
+
+            self.documents.lock().await.delete(path);
+            let mut current_file = self.current_file.lock().await;
+            if is_within(path, &current_file.uri) {
+                current_file.uri.clear();
+            }
+        }
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let mut projects = self.projects.lock().await;
+
+        for removed in params.event.removed {
+            let removed = Project::from(removed);
+            projects.retain(|p| p.root != removed.root);
+        }
+        for added in params.event.added {
+            let added = Project::from(added);
+            projects.retain(|p| p.root != added.root);
+            projects.push(added);
+        }
+    }
+}
+
+pub async fn run(http_client: reqwest::Client, config: &Config) -> anyhow::Result<()> {
+    let categorizer = Categorizer::new(&config.categories)?;
+    let settings = Settings::default();
+    let api = Arc::new(RwLock::new(settings.api(config)));
+    let options = settings.options(config)?;
+    let stdin = tokio::io::stdin();
+    let stdout = tokio::io::stdout();
+    // Events are still worth sending without it, e.g. on a read-only home.
+    let machine = Machine::load().ok();
+    let (service, socket) = LspService::new(|client| {
+        Arc::new(CairosLanguangeServer {
+            sender: Sender::spawn(client.clone(), http_client.clone(), api.clone()),
+            client,
+            http_client,
+            api,
+            current_file: Mutex::new(CurrentFile {
+                uri: String::new(),
+                sent: SentHeartbeats::default(),
+            }),
+            projects: Mutex::new(Vec::new()),
+            editor: Mutex::new(Editor::default()),
+            machine,
+            documents: Mutex::new(Documents::new(config.classification.clone())),
+            categorizer,
+            paused: AtomicBool::new(false),
+            config: config.clone(),
+            options: Mutex::new(options),
+        })
+    });
+
+    Server::new(stdin, stdout, socket).serve(service).await;
+
+    Ok(())
+}
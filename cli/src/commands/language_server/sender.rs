@@ -0,0 +1,198 @@
+use super::settings::Api;
+use crate::clients::cairos::{RenameEventsParams, SendEventsParams};
+use rand::Rng;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot};
+use tower_lsp::{Client, lsp_types::MessageType};
+
+/// Requests waiting to be sent, the oldest ones being dropped past it.
+const CAPACITY: usize = 1024;
+
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(300);
+
+/// How long exiting waits for the queue to be sent.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub enum Request {
+    Capture(Box<SendEventsParams>),
+    Rename(RenameEventsParams),
+}
+
+enum Message {
+    Request(Request),
+    /// Sends the queue once, without retries, then replies
+    Flush(oneshot::Sender<()>),
+}
+
+/// Queues requests to the API for a background task, so a slow or
+/// unreachable server never holds up the editor.
+#[derive(Clone)]
+pub struct Sender {
+    tx: mpsc::Sender<Message>,
+}
+
+impl Sender {
+    /// Sends to `api` as it is when each request goes out.
+    pub fn spawn(client: Client, http_client: reqwest::Client, api: Arc<RwLock<Api>>) -> Self {
+        let (tx, rx) = mpsc::channel(CAPACITY);
+
+        let worker = Worker {
+            client,
+            http_client,
+            api,
+            queue: VecDeque::new(),
+        };
+        tokio::spawn(worker.run(rx));
+
+        Self { tx }
+    }
+
+    /// Queues `request`, returning `false` when the queue is full.
+    pub fn send(&self, request: Request) -> bool {
+        self.tx.try_send(Message::Request(request)).is_ok()
+    }
+
+    /// Waits, up to a few seconds, for the queued requests to be sent once.
+    pub async fn flush(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(Message::Flush(tx)).await.is_err() {
+            return;
+        }
+
+        let _ = tokio::time::timeout(FLUSH_TIMEOUT, rx).await;
+    }
+}
+
+struct Worker {
+    client: Client,
+    http_client: reqwest::Client,
+    api: Arc<RwLock<Api>>,
+    queue: VecDeque<Request>,
+}
+
+impl Worker {
+    async fn run(mut self, mut rx: mpsc::Receiver<Message>) {
+        let mut attempts = 0;
+
+        loop {
+            if self.queue.is_empty() {
+                match rx.recv().await {
+                    Some(message) => self.accept(message).await,
+                    None => return,
+                }
+            }
+            while let Ok(message) = rx.try_recv() {
+                self.accept(message).await;
+            }
+
+            let Some(request) = self.queue.front() else {
+                continue;
+            };
+
+            match self.deliver(request).await {
+                Err(e) if e.is_retryable() => {
+                    attempts += 1;
+                    if attempts == 1 {
+                        self.log_error(&e).await;
+                    }
+
+                    // Keeps queueing, and flushing, while waiting.
+                    let retry = tokio::time::sleep(backoff(attempts));
+                    tokio::pin!(retry);
+                    loop {
+                        tokio::select! {
+                            _ = &mut retry => break,
+                            message = rx.recv() => match message {
+                                Some(message) => self.accept(message).await,
+                                None => return,
+                            },
+                        }
+                    }
+                }
+                result => {
+                    if let Err(e) = result {
+                        self.log_error(&e).await;
+                    }
+                    self.queue.pop_front();
+                    attempts = 0;
+                }
+            }
+        }
+    }
+
+    async fn accept(&mut self, message: Message) {
+        match message {
+            Message::Request(request) => self.push(request),
+            Message::Flush(done) => {
+                while let Some(request) = self.queue.pop_front() {
+                    if let Err(e) = self.deliver(&request).await {
+                        self.log_error(&e).await;
+                    }
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+
+    /// Queues `request`, merging it into the previous event when both are
+    /// for the same file at about the same time, and dropping the oldest
+    /// ones past `CAPACITY` while the API is unreachable.
+    fn push(&mut self, request: Request) {
+        if let (Request::Capture(event), Some(Request::Capture(previous))) =
+            (&request, self.queue.back_mut())
+            && previous.merge(event)
+        {
+            return;
+        }
+
+        self.queue.push_back(request);
+        while self.queue.len() > CAPACITY {
+            self.queue.pop_front();
+        }
+    }
+
+    async fn deliver(&self, request: &Request) -> Result<(), crate::clients::Error> {
+        let api = self.api.read().expect("API settings lock poisoned").clone();
+        let token = api.token.as_deref().unwrap_or_default();
+
+        match request {
+            Request::Capture(event) => {
+                crate::clients::cairos::send_events(&self.http_client, &api.base_url, token, event)
+                    .await
+            }
+            Request::Rename(rename) => {
+                crate::clients::cairos::rename_events(
+                    &self.http_client,
+                    &api.base_url,
+                    token,
+                    rename,
+                )
+                .await
+            }
+        }
+    }
+
+    async fn log_error(&self, e: &crate::clients::Error) {
+        self.client
+            .log_message(
+                MessageType::ERROR,
+                format!("Error when trying to send events: {e:?}"),
+            )
+            .await;
+    }
+}
+
+/// Exponential backoff capped at a few minutes, with jitter so editors
+/// don't all retry at once after an outage.
+fn backoff(attempts: u32) -> Duration {
+    let max = FIRST_RETRY
+        .saturating_mul(2_u32.saturating_pow(attempts - 1))
+        .min(MAX_RETRY);
+
+    max.mul_f64(rand::rng().random_range(0.5..=1.0))
+}
@@ -0,0 +1,82 @@
+use crate::config::{Config, Dedupe, Heartbeat, Privacy, exclude_set};
+use globset::GlobSet;
+use serde::Deserialize;
+
+/// Settings an editor passes in `initializationOptions` and
+/// `workspace/didChangeConfiguration`, taking precedence over config.toml.
+/// Either at the top level or under a `cairos` key.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    server_url: Option<String>,
+    token: Option<String>,
+    privacy: Option<Privacy>,
+    exclude: Option<Vec<String>>,
+    heartbeat: HeartbeatSettings,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct HeartbeatSettings {
+    interval_seconds: Option<u64>,
+    send_on_save: Option<bool>,
+    dedupe: Option<Dedupe>,
+}
+
+/// Where events are sent.
+#[derive(Clone)]
+pub struct Api {
+    pub base_url: String,
+    pub token: Option<String>,
+}
+
+/// Which events are sent, when and with how much of their paths.
+pub struct Options {
+    pub privacy: Privacy,
+    pub exclude: GlobSet,
+    pub heartbeat: Heartbeat,
+}
+
+impl Settings {
+    pub fn from_value(mut value: serde_json::Value) -> anyhow::Result<Self> {
+        if let Some(cairos) = value.get_mut("cairos") {
+            value = cairos.take();
+        }
+        if value.is_null() {
+            return Ok(Self::default());
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn api(&self, config: &Config) -> Api {
+        Api {
+            base_url: self
+                .server_url
+                .clone()
+                .unwrap_or_else(|| config.base_url.clone())
+                .trim_end_matches('/')
+                .to_owned(),
+            token: self.token.clone().or_else(|| config.token.clone()),
+        }
+    }
+
+    /// `config` with the settings that are set applied over it.
+    pub fn options(&self, config: &Config) -> anyhow::Result<Options> {
+        let heartbeat = &self.heartbeat;
+
+        Ok(Options {
+            privacy: self.privacy.unwrap_or(config.privacy),
+            exclude: exclude_set(self.exclude.as_ref().unwrap_or(&config.exclude))?,
+            heartbeat: Heartbeat {
+                interval_seconds: heartbeat
+                    .interval_seconds
+                    .unwrap_or(config.heartbeat.interval_seconds),
+                send_on_save: heartbeat
+                    .send_on_save
+                    .unwrap_or(config.heartbeat.send_on_save),
+                dedupe: heartbeat.dedupe.unwrap_or(config.heartbeat.dedupe),
+            },
+        })
+    }
+}
@@ -1,3 +1,7 @@
 pub mod auth;
 pub mod config;
+pub mod git_hook;
+pub mod heartbeat;
 pub mod language_server;
+pub mod report;
+pub mod shell_hook;
@@ -0,0 +1,41 @@
+use crate::{config::Config, format::format_duration};
+use anyhow::Context;
+
+/// Prints the time spent on each ticket over the last `days` days.
+pub async fn tickets(
+    reqwest: &reqwest::Client,
+    config: &Config,
+    project: Option<&str>,
+    days: i64,
+) -> anyhow::Result<()> {
+    let token = config
+        .token
+        .as_deref()
+        .context("You are not authenticated, run `cairos auth login --github` first")?;
+
+    let report =
+        crate::clients::cairos::get_tickets(reqwest, &config.base_url, token, project, days)
+            .await?;
+
+    if report.entries.is_empty() {
+        println!("No time on tickets in the last {days} days");
+        return Ok(());
+    }
+
+    let width = report
+        .entries
+        .iter()
+        .map(|entry| entry.ticket.len())
+        .max()
+        .unwrap_or_default();
+    for entry in &report.entries {
+        println!(
+            "{:width$}  {:>8}  {}",
+            entry.ticket,
+            format_duration(entry.seconds),
+            entry.projects.join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use super::heartbeat::{deliver, uri_of};
+use crate::{
+    category::Categorizer,
+    clients::cairos::{EventKind, SendEventsParams},
+    config::{Config, exclude_set},
+    machine::Machine,
+};
+use anyhow::Context;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Shell {
+    Zsh,
+    Bash,
+    Fish,
+}
+
+impl Shell {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Zsh => "zsh",
+            Self::Bash => "bash",
+            Self::Fish => "fish",
+        }
+    }
+}
+
+/// Remembers the command line and start time before each command, then
+/// reports them in the background once it finishes.
+const ZSH_HOOK: &str = r#"zmodload zsh/datetime
+autoload -Uz add-zsh-hook
+
+_cairos_preexec() {
+    _cairos_command=$1
+    _cairos_cwd=$PWD
+    _cairos_start=$EPOCHSECONDS
+}
+
+_cairos_precmd() {
+    [[ -n ${_cairos_command-} ]] || return
+    {cairos} shell-event --shell zsh --command "$_cairos_command" --cwd "$_cairos_cwd" --started-at "$_cairos_start" &>/dev/null &!
+    unset _cairos_command
+}
+
+add-zsh-hook preexec _cairos_preexec
+add-zsh-hook precmd _cairos_precmd
+"#;
+
+/// Bash has no preexec, so a DEBUG trap armed at the end of
+/// `PROMPT_COMMAND` catches the first command run after each prompt. It runs
+/// after the DEBUG trap of other tools, like bash-preexec or atuin, keeping
+/// its exit status for `extdebug`.
+const BASH_HOOK: &str = r#"_cairos_armed=
+
+_cairos_preexec() {
+    if [[ -n $_cairos_armed ]]; then
+        _cairos_armed=
+        if [[ $BASH_COMMAND != _cairos_precmd ]]; then
+            _cairos_command=$BASH_COMMAND
+            _cairos_cwd=$PWD
+            _cairos_start=${EPOCHSECONDS:-$(date +%s)}
+        fi
+    fi
+    return "${1:-0}"
+}
+
+_cairos_precmd() {
+    [[ -n ${_cairos_command-} ]] || return
+    ({cairos} shell-event --shell bash --command "$_cairos_command" --cwd "$_cairos_cwd" --started-at "$_cairos_start" &>/dev/null &)
+    unset _cairos_command
+}
+
+# At the top level of `eval`, as `trap -p` misses the DEBUG trap in functions
+# and sourced files.
+eval "_cairos_trap=($(trap -p DEBUG))"
+case ${_cairos_trap[2]-} in
+    *_cairos_preexec*) ;;
+    '') trap '_cairos_preexec' DEBUG ;;
+    *) trap "${_cairos_trap[2]}"$'\n''_cairos_preexec "$?"' DEBUG ;;
+esac
+unset _cairos_trap
+
+# Bash 5.1 also takes an array of commands.
+if [[ $(declare -p PROMPT_COMMAND 2>/dev/null) == "declare -a"* ]]; then
+    PROMPT_COMMAND=(_cairos_precmd "${PROMPT_COMMAND[@]}" _cairos_armed=1)
+else
+    PROMPT_COMMAND="_cairos_precmd"$'\n'"${PROMPT_COMMAND:-}"$'\n'"_cairos_armed=1"
+fi
+"#;
+
+const FISH_HOOK: &str = r#"function _cairos_preexec --on-event fish_preexec
+    set -g _cairos_command $argv[1]
+    set -g _cairos_cwd $PWD
+    set -g _cairos_start (date +%s)
+end
+
+function _cairos_postexec --on-event fish_postexec
+    set -q _cairos_command; or return
+    {cairos} shell-event --shell fish --command "$_cairos_command" --cwd "$_cairos_cwd" --started-at "$_cairos_start" &>/dev/null &
+    disown 2>/dev/null
+    set -e _cairos_command
+end
+"#;
+
+/// Prints the hook for `shell`, calling back this very binary.
+pub fn print(shell: Shell) -> anyhow::Result<()> {
+    let binary = std::env::current_exe().context("failed to locate the cairos binary")?;
+    let binary = binary.to_string_lossy();
+
+    let (hook, binary) = match shell {
+        Shell::Zsh => (ZSH_HOOK, quote_posix(&binary)),
+        Shell::Bash => (BASH_HOOK, quote_posix(&binary)),
+        Shell::Fish => (FISH_HOOK, quote_fish(&binary)),
+    };
+    print!("{}", hook.replace("{cairos}", &binary));
+
+    Ok(())
+}
+
+fn quote_posix(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+fn quote_fish(text: &str) -> String {
+    format!("'{}'", text.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Command reported by the hook once it finished.
+pub struct CommandRun {
+    pub shell: Shell,
+    /// Command line in zsh and fish, only its first simple command in bash
+    pub command: String,
+    pub cwd: PathBuf,
+    /// Unix time the command started at
+    pub started_at: i64,
+}
+
+/// Sends `run` as a terminal event, through the same exclusions, privacy
+/// and queue as `cairos heartbeat`. Only the program name is sent unless
+/// `terminal.include_arguments` is set.
+pub async fn record(
+    reqwest: &reqwest::Client,
+    config: &Config,
+    run: CommandRun,
+) -> anyhow::Result<()> {
+    let token = config
+        .token
+        .as_deref()
+        .context("You are not authenticated, run `cairos auth login --github` first")?;
+
+    let command = if config.terminal.include_arguments {
+        Some(run.command.trim())
+    } else {
+        program(&run.command)
+    };
+    let Some(command) = command.filter(|command| !command.is_empty()) else {
+        return Ok(());
+    };
+
+    let cwd = std::path::absolute(&run.cwd)
+        .with_context(|| format!("invalid directory {}", run.cwd.display()))?;
+    let uri = uri_of(&cwd)?;
+    if exclude_set(&config.exclude)?.is_match(&uri) {
+        return Ok(());
+    }
+
+    let started_at =
+        time::OffsetDateTime::from_unix_timestamp(run.started_at).context("invalid start time")?;
+    let ran = time::OffsetDateTime::now_utc() - started_at;
+    let categorizer = Categorizer::new(&config.categories)?;
+    let root = crate::git::repository_root(&cwd).and_then(|root| uri_of(root).ok());
+    let machine = Machine::load().ok();
+
+    let event = SendEventsParams {
+        uri: config.privacy.redact(&uri),
+        timestamp: started_at,
+        is_write: false,
+        language: None,
+        line_number: None,
+        cursor_pos: None,
+        project: crate::git::repository_name(&cwd),
+        editor: Some(run.shell.name().to_owned()),
+        editor_version: None,
+        client_version: env!("CARGO_PKG_VERSION").to_owned(),
+        os: std::env::consts::OS.to_owned(),
+        arch: std::env::consts::ARCH.to_owned(),
+        machine_id: machine.as_ref().map(|m| m.id.clone()),
+        hostname: machine.as_ref().and_then(|m| m.hostname.clone()),
+        lines_added: 0,
+        lines_removed: 0,
+        typed_chars: 0,
+        pasted_chars: 0,
+        bulk_chars: 0,
+        category: categorizer
+            .categorize(&uri, root.as_deref())
+            .as_str()
+            .to_owned(),
+        kind: EventKind::Terminal,
+        command: Some(command.to_owned()),
+        duration_seconds: Some(i32::try_from(ran.whole_seconds().max(0)).unwrap_or(i32::MAX)),
+        branch: config
+            .privacy
+            .keeps_branch()
+            .then(|| crate::git::branch(&cwd))
+            .flatten(),
+    };
+
+    deliver(reqwest, config, token, event).await
+}
+
+/// Program run by the command `line`, without its folder, skipping the
+/// `NAME=value` assignments before it.
+fn program(line: &str) -> Option<&str> {
+    let word = line.split_whitespace().find(|word| !is_assignment(word))?;
+
+    word.rsplit('/').next()
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
@@ -1,15 +1,195 @@
+use crate::category::CategoryRule;
+use anyhow::Context;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     env,
     fs::{self, File},
     io,
     path::PathBuf,
 };
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Config {
     pub base_url: String,
     pub token: Option<String>,
+    #[serde(default)]
+    pub privacy: Privacy,
+    /// Globs of files never tracked, matched against full paths
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub heartbeat: Heartbeat,
+    #[serde(default)]
+    pub classification: Classification,
+    #[serde(default)]
+    pub terminal: Terminal,
+    /// Checked in order before the categories inferred from paths
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<CategoryRule>,
+}
+
+/// How much of a file's path is sent with its events.
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Privacy {
+    #[default]
+    Full,
+    /// The file name, without its folders
+    FileName,
+    /// Nothing about the file but its project and language, nor the branch
+    /// or commit subjects
+    ProjectOnly,
+}
+
+/// Sent instead of paths with the `project_only` privacy level.
+const HIDDEN_PATH: &str = "(hidden)";
+
+impl Privacy {
+    /// Path sent for `path`.
+    pub fn redact(self, path: &str) -> String {
+        match self {
+            Self::Full => path.to_owned(),
+            Self::FileName => path.rsplit('/').next().unwrap_or(path).to_owned(),
+            Self::ProjectOnly => HIDDEN_PATH.to_owned(),
+        }
+    }
+
+    /// Whether line and cursor positions are sent.
+    pub fn keeps_position(self) -> bool {
+        !matches!(self, Self::ProjectOnly)
+    }
+
+    /// Whether the git branch and commit subjects are sent, as they often
+    /// tell what the files are about.
+    pub fn keeps_branch(self) -> bool {
+        !matches!(self, Self::ProjectOnly)
+    }
+}
+
+/// Matcher of the `exclude` globs.
+pub fn exclude_set(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("invalid exclude pattern `{pattern}`"))?;
+        builder.add(glob);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// When a heartbeat is sent, editor settings can override it.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Heartbeat {
+    /// Seconds before a heartbeat deduplicated with the previous one is sent
+    pub interval_seconds: u64,
+    /// Saves are sent even within the interval
+    pub send_on_save: bool,
+    pub dedupe: Dedupe,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 120,
+            send_on_save: true,
+            dedupe: Dedupe::File,
+        }
+    }
+}
+
+impl Heartbeat {
+    /// Whether a heartbeat for `uri` is sent, given the ones already `sent`.
+    pub fn is_due(
+        &self,
+        sent: &SentHeartbeats,
+        uri: &str,
+        is_write: bool,
+        now: time::OffsetDateTime,
+    ) -> bool {
+        let last = match self.dedupe {
+            Dedupe::File => sent.files.get(uri).copied(),
+            Dedupe::Global => sent.last,
+        };
+
+        last.is_none_or(|last| now - last >= self.interval()) || (is_write && self.send_on_save)
+    }
+
+    fn interval(&self) -> time::Duration {
+        time::Duration::seconds(i64::try_from(self.interval_seconds).unwrap_or(i64::MAX))
+    }
+}
+
+/// When heartbeats were last sent, overall and per file, for the debounce.
+#[derive(Default, Deserialize, Serialize)]
+pub struct SentHeartbeats {
+    /// Last one, whatever the file, for `Dedupe::Global`
+    last: Option<time::OffsetDateTime>,
+    /// Last one of each file still within the interval, for `Dedupe::File`
+    files: HashMap<String, time::OffsetDateTime>,
+}
+
+impl SentHeartbeats {
+    pub fn record(&mut self, uri: &str, now: time::OffsetDateTime, heartbeat: &Heartbeat) {
+        let interval = heartbeat.interval();
+        self.files.retain(|_, sent_at| now - *sent_at < interval);
+        self.files.insert(uri.to_owned(), now);
+        self.last = Some(now);
+    }
+
+    /// Lets the next heartbeat of `uri` through right away.
+    pub fn forget(&mut self, uri: &str) {
+        self.files.remove(uri);
+        self.last = None;
+    }
+}
+
+/// Heartbeats waiting for the interval before being sent.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dedupe {
+    /// Only those for the same file as the previous one
+    File,
+    /// All of them, whatever the file
+    Global,
+}
+
+/// Thresholds telling typed insertions apart from pasted and bulk ones.
+/// Sizes count non-whitespace characters, so auto-indentation stays typed.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Classification {
+    /// Largest single insertion still counted as typed, e.g. a completion
+    pub typed_max_chars: usize,
+    /// Smallest single insertion counted as bulk or AI generated
+    pub bulk_min_chars: usize,
+    /// Insertions larger than typed arriving faster than this, like a
+    /// streamed completion, count as bulk
+    pub bulk_chars_per_second: f64,
+}
+
+impl Default for Classification {
+    fn default() -> Self {
+        Self {
+            typed_max_chars: 16,
+            bulk_min_chars: 400,
+            bulk_chars_per_second: 100.0,
+        }
+    }
+}
+
+/// What the shell hook records of the commands run.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Terminal {
+    /// Send whole command lines instead of the program name, arguments
+    /// often holding paths, hosts or secrets
+    pub include_arguments: bool,
 }
 
 impl Config {
@@ -22,21 +202,31 @@ impl Config {
         Self {
             base_url: config.base_url,
             token: config.token,
+            privacy: config.privacy,
+            exclude: config.exclude,
+            heartbeat: config.heartbeat,
+            classification: config.classification,
+            terminal: config.terminal,
+            categories: config.categories,
         }
     }
 }
 
 pub fn get_or_create_config_content(config_file_path: PathBuf) -> io::Result<String> {
     if !config_file_path.exists() {
-        config_file_path
-            .parent()
-            .map(|parent| fs::create_dir_all(parent));
+        config_file_path.parent().map(fs::create_dir_all);
 
         File::create(&config_file_path)?;
 
         let my_config = Config {
             base_url: "https://localhost".to_owned(),
             token: None,
+            privacy: Privacy::default(),
+            exclude: Vec::new(),
+            heartbeat: Heartbeat::default(),
+            classification: Classification::default(),
+            terminal: Terminal::default(),
+            categories: Vec::new(),
         };
 
         let toml_content = toml::to_string_pretty(&my_config).expect("Toml serialization failed");
@@ -0,0 +1,10 @@
+/// Formats a number of seconds as `3h 05m`, or `12m` when under an hour, the
+/// way the dashboard shows durations.
+pub fn format_duration(seconds: i64) -> String {
+    let minutes = seconds / 60;
+
+    match minutes / 60 {
+        0 => format!("{minutes}m"),
+        hours => format!("{hours}h {:02}m", minutes % 60),
+    }
+}
@@ -0,0 +1,42 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Innermost git repository containing `path`, or being it.
+pub fn repository_root(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|dir| dir.join(".git").exists())
+}
+
+/// Folder name of the repository containing `path`, reported as its project.
+pub fn repository_name(path: &Path) -> Option<String> {
+    repository_root(path)?
+        .file_name()?
+        .to_str()
+        .map(str::to_owned)
+}
+
+/// Branch checked out in the repository containing `path`, read from
+/// `HEAD` without running git. `None` on a detached `HEAD`.
+pub fn branch(path: &Path) -> Option<String> {
+    let root = repository_root(path)?;
+    let head = fs::read_to_string(git_dir(root)?.join("HEAD")).ok()?;
+
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(str::to_owned)
+}
+
+/// `.git` itself, or the folder a worktree's or submodule's `.git` file
+/// points to.
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let dir = content.trim().strip_prefix("gitdir:")?.trim();
+
+    Some(root.join(dir))
+}
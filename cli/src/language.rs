@@ -0,0 +1,408 @@
+use std::path::Path;
+
+struct Language {
+    /// Lowercase, as the API stores languages
+    name: &'static str,
+    /// Editor language ids, compared ignoring case, spaces, dashes and underscores
+    aliases: &'static [&'static str],
+    extensions: &'static [&'static str],
+    /// Interpreters named by a shebang, version suffixes ignored
+    interpreters: &'static [&'static str],
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "c",
+        aliases: &[],
+        extensions: &["c", "h"],
+        interpreters: &[],
+    },
+    Language {
+        name: "csharp",
+        aliases: &["c#", "cs"],
+        extensions: &["cs", "csx"],
+        interpreters: &[],
+    },
+    Language {
+        name: "cpp",
+        aliases: &["c++", "cxx"],
+        extensions: &["cc", "cpp", "cxx", "c++", "hh", "hpp", "hxx"],
+        interpreters: &[],
+    },
+    Language {
+        name: "css",
+        aliases: &[],
+        extensions: &["css"],
+        interpreters: &[],
+    },
+    Language {
+        name: "dart",
+        aliases: &[],
+        extensions: &["dart"],
+        interpreters: &["dart"],
+    },
+    Language {
+        name: "dockerfile",
+        aliases: &["docker"],
+        extensions: &["dockerfile"],
+        interpreters: &[],
+    },
+    Language {
+        name: "elixir",
+        aliases: &[],
+        extensions: &["ex", "exs"],
+        interpreters: &["elixir"],
+    },
+    Language {
+        name: "erlang",
+        aliases: &[],
+        extensions: &["erl", "hrl"],
+        interpreters: &["escript"],
+    },
+    Language {
+        name: "fish",
+        aliases: &[],
+        extensions: &["fish"],
+        interpreters: &["fish"],
+    },
+    Language {
+        name: "go",
+        aliases: &["golang"],
+        extensions: &["go"],
+        interpreters: &[],
+    },
+    Language {
+        name: "graphql",
+        aliases: &[],
+        extensions: &["graphql", "gql"],
+        interpreters: &[],
+    },
+    Language {
+        name: "haskell",
+        aliases: &[],
+        extensions: &["hs"],
+        interpreters: &["runhaskell"],
+    },
+    Language {
+        name: "html",
+        aliases: &[],
+        extensions: &["html", "htm"],
+        interpreters: &[],
+    },
+    Language {
+        name: "java",
+        aliases: &[],
+        extensions: &["java"],
+        interpreters: &[],
+    },
+    Language {
+        name: "javascript",
+        aliases: &["js", "javascriptreact", "jsx"],
+        extensions: &["js", "mjs", "cjs", "jsx"],
+        interpreters: &["node", "nodejs"],
+    },
+    Language {
+        name: "json",
+        aliases: &["jsonc", "jsonwithcomments", "json5"],
+        extensions: &["json", "jsonc", "json5"],
+        interpreters: &[],
+    },
+    Language {
+        name: "kotlin",
+        aliases: &[],
+        extensions: &["kt", "kts"],
+        interpreters: &[],
+    },
+    Language {
+        name: "lua",
+        aliases: &[],
+        extensions: &["lua"],
+        interpreters: &["lua", "luajit"],
+    },
+    Language {
+        name: "makefile",
+        aliases: &["make"],
+        extensions: &["mk"],
+        interpreters: &["make"],
+    },
+    Language {
+        name: "markdown",
+        aliases: &["md"],
+        extensions: &["md", "markdown", "mdx"],
+        interpreters: &[],
+    },
+    Language {
+        name: "nix",
+        aliases: &[],
+        extensions: &["nix"],
+        interpreters: &[],
+    },
+    Language {
+        name: "ocaml",
+        aliases: &[],
+        extensions: &["ml", "mli"],
+        interpreters: &["ocaml"],
+    },
+    Language {
+        name: "perl",
+        aliases: &[],
+        extensions: &["pl", "pm"],
+        interpreters: &["perl"],
+    },
+    Language {
+        name: "php",
+        aliases: &[],
+        extensions: &["php"],
+        interpreters: &["php"],
+    },
+    Language {
+        name: "python",
+        aliases: &["py"],
+        extensions: &["py", "pyi", "pyw"],
+        interpreters: &["python"],
+    },
+    Language {
+        name: "r",
+        aliases: &[],
+        extensions: &["r"],
+        interpreters: &["rscript"],
+    },
+    Language {
+        name: "ruby",
+        aliases: &["rb"],
+        extensions: &["rb", "rake", "gemspec"],
+        interpreters: &["ruby"],
+    },
+    Language {
+        name: "rust",
+        aliases: &["rs"],
+        extensions: &["rs"],
+        interpreters: &[],
+    },
+    Language {
+        name: "scala",
+        aliases: &[],
+        extensions: &["scala", "sc"],
+        interpreters: &["scala"],
+    },
+    Language {
+        name: "scss",
+        aliases: &["sass"],
+        extensions: &["scss", "sass"],
+        interpreters: &[],
+    },
+    Language {
+        name: "shell",
+        aliases: &["shellscript", "sh", "bash", "zsh", "ksh", "dash"],
+        extensions: &["sh", "bash", "zsh", "ksh"],
+        interpreters: &["sh", "bash", "zsh", "ksh", "dash"],
+    },
+    Language {
+        name: "sql",
+        aliases: &["pgsql", "postgres", "mysql", "plsql"],
+        extensions: &["sql"],
+        interpreters: &[],
+    },
+    Language {
+        name: "svelte",
+        aliases: &[],
+        extensions: &["svelte"],
+        interpreters: &[],
+    },
+    Language {
+        name: "swift",
+        aliases: &[],
+        extensions: &["swift"],
+        interpreters: &["swift"],
+    },
+    Language {
+        name: "toml",
+        aliases: &[],
+        extensions: &["toml"],
+        interpreters: &[],
+    },
+    Language {
+        name: "typescript",
+        aliases: &["ts", "typescriptreact", "tsx"],
+        extensions: &["ts", "mts", "cts", "tsx"],
+        interpreters: &["deno", "bun", "tsnode", "tsx"],
+    },
+    Language {
+        name: "vue",
+        aliases: &[],
+        extensions: &["vue"],
+        interpreters: &[],
+    },
+    Language {
+        name: "xml",
+        aliases: &["xsl"],
+        extensions: &["xml", "xsd", "xsl", "svg"],
+        interpreters: &[],
+    },
+    Language {
+        name: "yaml",
+        aliases: &["yml"],
+        extensions: &["yaml", "yml"],
+        interpreters: &[],
+    },
+    Language {
+        name: "zig",
+        aliases: &[],
+        extensions: &["zig"],
+        interpreters: &[],
+    },
+];
+
+/// File names whose extension, if any, says nothing about their language.
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("dockerfile", "dockerfile"),
+    ("containerfile", "dockerfile"),
+    ("makefile", "makefile"),
+    ("gnumakefile", "makefile"),
+    ("rakefile", "ruby"),
+    ("gemfile", "ruby"),
+    (".bashrc", "shell"),
+    (".bash_profile", "shell"),
+    (".zshrc", "shell"),
+    (".profile", "shell"),
+];
+
+/// Ids editors send for documents without a language.
+const PLAIN_TEXT: &[&str] = &["plaintext", "text", "txt"];
+
+/// Canonical id of an editor language id, e.g. `shellscript`, `sh` and
+/// `Shell Script` all give `shell`. Unknown ids are kept as sent, plain text
+/// gives `None`.
+pub fn normalize(id: &str) -> Option<String> {
+    let id = id.trim();
+    let key = key(id);
+    if key.is_empty() || PLAIN_TEXT.contains(&key.as_str()) {
+        return None;
+    }
+
+    let name = LANGUAGES
+        .iter()
+        .find(|l| key == l.name || l.aliases.contains(&key.as_str()))
+        .map_or(id, |l| l.name);
+
+    Some(name.to_owned())
+}
+
+/// Language of the file at `path`, from its name, then from the shebang of
+/// `text` when known.
+pub fn detect(path: &str, text: Option<&str>) -> Option<String> {
+    from_path(path)
+        .or_else(|| text.and_then(from_shebang))
+        .map(str::to_owned)
+}
+
+fn from_path(path: &str) -> Option<&'static str> {
+    let path = Path::new(path);
+    let file_name = path.file_name()?.to_str()?.to_lowercase();
+
+    if let Some((_, name)) = FILE_NAMES.iter().find(|(f, _)| *f == file_name) {
+        return Some(name);
+    }
+
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|l| l.extensions.contains(&extension.as_str()))
+        .map(|l| l.name)
+}
+
+/// Language of the interpreter named by `#!/usr/bin/python3`,
+/// `#!/usr/bin/env -S node --flag` and the like.
+fn from_shebang(text: &str) -> Option<&'static str> {
+    let line = text.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+
+    let interpreter = key(program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'));
+    LANGUAGES
+        .iter()
+        .find(|l| l.interpreters.contains(&interpreter.as_str()))
+        .map(|l| l.name)
+}
+
+/// Lowercase `id` without spaces, dashes and underscores.
+fn key(id: &str) -> String {
+    id.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_aliases() {
+        assert_eq!(normalize("shellscript").as_deref(), Some("shell"));
+        assert_eq!(normalize("Shell Script").as_deref(), Some("shell"));
+        assert_eq!(normalize("sh").as_deref(), Some("shell"));
+        assert_eq!(normalize("typescriptreact").as_deref(), Some("typescript"));
+        assert_eq!(normalize("C#").as_deref(), Some("csharp"));
+        assert_eq!(normalize(" RUST ").as_deref(), Some("rust"));
+    }
+
+    #[test]
+    fn normalize_keeps_unknown_ids_and_drops_plain_text() {
+        assert_eq!(normalize("Gleam").as_deref(), Some("Gleam"));
+        assert_eq!(normalize("Cap'n Proto").as_deref(), Some("Cap'n Proto"));
+        assert_eq!(normalize("plaintext"), None);
+        assert_eq!(normalize("Plain Text"), None);
+        assert_eq!(normalize("  "), None);
+    }
+
+    #[test]
+    fn detect_from_path() {
+        assert_eq!(detect("/p/src/main.rs", None).as_deref(), Some("rust"));
+        assert_eq!(detect("/p/App.TSX", None).as_deref(), Some("typescript"));
+        assert_eq!(detect("/p/ñandú.py", None).as_deref(), Some("python"));
+        assert_eq!(detect("/p/Dockerfile", None).as_deref(), Some("dockerfile"));
+        assert_eq!(detect("/p/Makefile", None).as_deref(), Some("makefile"));
+        assert_eq!(detect("/home/me/.zshrc", None).as_deref(), Some("shell"));
+    }
+
+    #[test]
+    fn detect_from_shebang() {
+        let detect = |text| detect("/p/bin/tool", Some(text));
+
+        assert_eq!(detect("#!/bin/bash\necho").as_deref(), Some("shell"));
+        assert_eq!(
+            detect("#!/usr/bin/python3.11\r\n").as_deref(),
+            Some("python")
+        );
+        assert_eq!(
+            detect("#!/usr/bin/env -S node --no-warnings\n").as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(
+            detect("#!/usr/bin/env FOO=1 ts-node\n").as_deref(),
+            Some("typescript")
+        );
+        assert_eq!(detect("echo\n#!/bin/bash").as_deref(), None);
+    }
+
+    #[test]
+    fn detect_prefers_the_path_over_the_shebang() {
+        assert_eq!(
+            detect("/p/script.rb", Some("#!/usr/bin/env python\n")).as_deref(),
+            Some("ruby")
+        );
+    }
+
+    #[test]
+    fn detect_unknown_languages() {
+        assert_eq!(detect("/p/data.xyz", None), None);
+        assert_eq!(detect("/p/tool", None), None);
+        assert_eq!(detect("/p/tool", Some("#!/usr/bin/awk -f\n")), None);
+        assert_eq!(detect("", Some("")), None);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::config::get_config_file_path;
+use anyhow::Context;
+use std::fs;
+
+/// Computer the events are sent from.
+pub struct Machine {
+    /// Random ID kept next to the config file, stable across hostname changes
+    pub id: String,
+    pub hostname: Option<String>,
+}
+
+impl Machine {
+    /// Reads the machine ID, creating it on first use.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = get_config_file_path()
+            .context("Config directory not found")?
+            .with_file_name("machine_id");
+
+        let id = match fs::read_to_string(&path) {
+            Ok(id) if !id.trim().is_empty() => id.trim().to_owned(),
+            _ => {
+                let id = uuid::Uuid::new_v4().to_string();
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).context("failed to create config directory")?;
+                }
+                fs::write(&path, &id).context("failed to write machine ID")?;
+                id
+            }
+        };
+
+        Ok(Self {
+            id,
+            hostname: whoami::fallible::hostname().ok(),
+        })
+    }
+}
@@ -1,9 +1,15 @@
 use clap::Parser;
+use std::time::Duration;
 
+mod category;
 mod cli;
 mod clients;
 mod commands;
 mod config;
+mod format;
+mod git;
+mod language;
+mod machine;
 
 pub struct Ctx {
     pub reqwest: reqwest::Client,
@@ -15,6 +21,10 @@ impl Ctx {
         Self {
             reqwest: reqwest::Client::builder()
                 .user_agent("cairos-cli")
+                // Hooks and the language server wait on it, an unresponsive
+                // server must not hold them up.
+                .connect_timeout(Duration::from_secs(5))
+                .timeout(Duration::from_secs(30))
                 .build()
                 .expect("Error when trying to build HTTP client"),
             config: config::Config::load(),
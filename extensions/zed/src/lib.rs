@@ -1,24 +1,213 @@
-use zed_extension_api::{self, Command, LanguageServerId, Result, Worktree};
+use std::fs;
+use zed_extension_api::{
+    self, Architecture, Command, DownloadedFileType, GithubReleaseOptions, LanguageServerId,
+    LanguageServerInstallationStatus, Os, Result, Worktree,
+    serde_json::{Map, Value},
+    settings::LspSettings,
+};
 
-struct CairosExtension;
+const BINARY_NAME: &str = "cairos";
+const REPOSITORY: &str = "samueldurantes/cairos";
+
+struct CairosExtension {
+    /// Downloaded binary, checked once per session
+    cached_binary_path: Option<String>,
+}
+
+impl CairosExtension {
+    /// `cairos` on the worktree's `PATH`, then the `lsp.cairos.binary.path`
+    /// setting, then the latest release downloaded to the work directory.
+    fn binary_path(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<String> {
+        if let Some(path) = worktree.which(BINARY_NAME) {
+            return Ok(path);
+        }
+
+        let configured = LspSettings::for_worktree(BINARY_NAME, worktree)
+            .ok()
+            .and_then(|settings| settings.binary)
+            .and_then(|binary| binary.path);
+        if let Some(path) = configured {
+            return Ok(path);
+        }
+
+        if let Some(path) = &self.cached_binary_path
+            && fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+        {
+            return Ok(path.clone());
+        }
+
+        let path = download(language_server_id).inspect_err(|e| {
+            zed_extension_api::set_language_server_installation_status(
+                language_server_id,
+                &LanguageServerInstallationStatus::Failed(e.clone()),
+            );
+        })?;
+        self.cached_binary_path = Some(path.clone());
+
+        Ok(path)
+    }
+}
+
+/// `lsp.cairos.initialization_options` with `lsp.cairos.settings` over it:
+/// server URL, token, privacy level, exclude globs and heartbeat policy,
+/// preferred by the language server over config.toml.
+fn cairos_settings(worktree: &Worktree) -> Result<Option<Value>> {
+    let settings = LspSettings::for_worktree(BINARY_NAME, worktree)?;
+
+    let mut merged = Map::new();
+    for value in [settings.initialization_options, settings.settings]
+        .into_iter()
+        .flatten()
+    {
+        match value {
+            Value::Object(object) => merged.extend(object),
+            Value::Null => {}
+            _ => return Err("Cairos settings must be an object".to_owned()),
+        }
+    }
+
+    Ok((!merged.is_empty()).then_some(Value::Object(merged)))
+}
+
+/// Downloads the release asset for this platform into `cairos-<version>`,
+/// unless already there, and removes the other versions.
+fn download(language_server_id: &LanguageServerId) -> Result<String> {
+    zed_extension_api::set_language_server_installation_status(
+        language_server_id,
+        &LanguageServerInstallationStatus::CheckingForUpdate,
+    );
+
+    let release = match zed_extension_api::latest_github_release(
+        REPOSITORY,
+        GithubReleaseOptions {
+            require_assets: true,
+            pre_release: false,
+        },
+    ) {
+        Ok(release) => release,
+        // Offline, a version downloaded before still works.
+        Err(e) => return downloaded().ok_or(e),
+    };
+
+    let (os, arch) = zed_extension_api::current_platform();
+    let target = format!(
+        "{arch}-{os}",
+        arch = match arch {
+            Architecture::Aarch64 => "aarch64",
+            Architecture::X86 => "x86",
+            Architecture::X8664 => "x86_64",
+        },
+        os = match os {
+            Os::Mac => "apple-darwin",
+            Os::Linux => "unknown-linux-gnu",
+            Os::Windows => "pc-windows-msvc",
+        },
+    );
+    let (extension, file_type, binary) = match os {
+        Os::Windows => ("zip", DownloadedFileType::Zip, format!("{BINARY_NAME}.exe")),
+        Os::Mac | Os::Linux => (
+            "tar.gz",
+            DownloadedFileType::GzipTar,
+            BINARY_NAME.to_owned(),
+        ),
+    };
+
+    let asset_name = format!("{BINARY_NAME}-{target}.{extension}");
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name == asset_name)
+        .ok_or_else(|| format!("No release asset found for {target}"))?;
+
+    let version_dir = format!("{BINARY_NAME}-{}", release.version);
+    let binary_path = format!("{version_dir}/{binary}");
+
+    if !fs::metadata(&binary_path).is_ok_and(|metadata| metadata.is_file()) {
+        zed_extension_api::set_language_server_installation_status(
+            language_server_id,
+            &LanguageServerInstallationStatus::Downloading,
+        );
+
+        zed_extension_api::download_file(&asset.download_url, &version_dir, file_type)
+            .map_err(|e| format!("Failed to download {asset_name}: {e}"))?;
+        zed_extension_api::make_file_executable(&binary_path)?;
+
+        let entries =
+            fs::read_dir(".").map_err(|e| format!("Failed to list work directory: {e}"))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if name.starts_with(&format!("{BINARY_NAME}-")) && name != version_dir {
+                fs::remove_dir_all(entry.path()).ok();
+            }
+        }
+    }
+
+    zed_extension_api::set_language_server_installation_status(
+        language_server_id,
+        &LanguageServerInstallationStatus::None,
+    );
+
+    Ok(binary_path)
+}
+
+/// Binary of a version downloaded in a previous session, if any.
+fn downloaded() -> Option<String> {
+    fs::read_dir(".")
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(&format!("{BINARY_NAME}-")))
+        .flat_map(|dir| {
+            [
+                format!("{dir}/{BINARY_NAME}"),
+                format!("{dir}/{BINARY_NAME}.exe"),
+            ]
+        })
+        .find(|path| fs::metadata(path).is_ok_and(|metadata| metadata.is_file()))
+}
 
 impl zed_extension_api::Extension for CairosExtension {
     fn new() -> Self {
-        Self {}
+        Self {
+            cached_binary_path: None,
+        }
     }
 
     fn language_server_command(
         &mut self,
-        _language_server_id: &LanguageServerId,
+        language_server_id: &LanguageServerId,
         worktree: &Worktree,
     ) -> Result<Command> {
         Ok(Command {
             args: vec!["language-server".to_owned()],
-            // TODO: Download binary before start the extension
-            command: String::from("/Users/samuel/workspace/cairos/target/debug/cli"),
+            command: self.binary_path(language_server_id, worktree)?,
             env: worktree.shell_env(),
         })
     }
+
+    fn language_server_initialization_options(
+        &mut self,
+        _language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<Option<Value>> {
+        cairos_settings(worktree)
+    }
+
+    /// Sent again on `workspace/didChangeConfiguration` when settings change.
+    fn language_server_workspace_configuration(
+        &mut self,
+        _language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<Option<Value>> {
+        cairos_settings(worktree)
+    }
 }
 
 zed_extension_api::register_extension!(CairosExtension);